@@ -0,0 +1,148 @@
+use crate::importation::InfectionSource;
+use crate::interventions::{run_model_with_interventions, Intervention};
+use crate::random_graphs::*;
+use crate::run_model::run_model;
+use crate::seeding::SeedingError;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+
+// Cases are detected at symptom onset, the start of the infectious period unless the extended
+// natural history is used, in which case asymptomatic infections go undetected. Their
+// contacts are found and quarantined after delays, during which their edges do not transmit.
+
+#[derive(Clone, Debug)]
+pub struct ContactTracing {
+    pub detection_probability: f64,
+    // days from symptom onset to the case being isolated and its contacts traced
+    pub detection_delay: usize,
+    // chance of finding each contact, and days from finding them to their quarantine
+    pub trace_probability: f64,
+    pub trace_delay: usize,
+    pub quarantine_days: usize,
+    // share of cases and contacts that actually isolate
+    pub compliance: f64,
+    // trace the contacts of a case, and the person who infected them
+    pub forward: bool,
+    pub backward: bool,
+    // one traces the contacts of cases, two also the contacts of those contacts, and so on
+    pub depth: usize,
+    pub quarantined: Vec<usize>,
+    // day due, node and number of tracing steps from a detected case
+    pending: Vec<(usize, usize, usize)>,
+    infectious: Vec<bool>,
+    rng: StdRng
+}
+
+impl ContactTracing {
+
+    pub fn new(detection_probability: f64, detection_delay: usize, trace_probability: f64, trace_delay: usize, quarantine_days: usize, compliance: f64) -> ContactTracing {
+        ContactTracing {
+            detection_probability,
+            detection_delay,
+            trace_probability,
+            trace_delay,
+            quarantine_days,
+            compliance,
+            forward: true,
+            backward: false,
+            depth: 1,
+            quarantined: Vec::new(),
+            pending: Vec::new(),
+            infectious: Vec::new(),
+            rng: StdRng::from_entropy()
+        }
+    }
+
+    pub fn seed(&mut self, seed: u64) {
+        // tracing draws from its own generator, so it does not shift the draws of the epidemic
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+}
+
+impl Intervention for ContactTracing {
+    fn before_step(&mut self, network_structure: &mut NetworkStructure, network_properties: &mut NetworkProperties, day: usize) {
+        let n = network_structure.degree.len();
+        if network_properties.quarantine.len() < n {
+            network_properties.quarantine.resize(n, 0);
+        }
+        network_properties.quarantine.iter_mut().for_each(|x| *x = x.saturating_sub(1));
+
+        // pending nodes are processed in the order they were found, including any found today
+        let mut neighbours: Vec<Vec<(usize, usize)>> = Vec::new();
+        let mut idx = 0;
+        while idx < self.pending.len() {
+            let (due, node, level) = self.pending[idx];
+            if due > day {
+                idx += 1;
+                continue;
+            }
+            self.pending.swap_remove(idx);
+            if self.rng.gen::<f64>() < self.compliance {
+                network_properties.quarantine[node] = network_properties.quarantine[node].max(self.quarantine_days);
+            }
+            if level >= self.depth {
+                continue;
+            }
+            if self.forward {
+                if neighbours.is_empty() {
+                    neighbours = network_structure.neighbours();
+                }
+                for &(j, _) in neighbours[node].iter() {
+                    if self.rng.gen::<f64>() < self.trace_probability {
+                        self.pending.push((day + self.trace_delay, j, level + 1));
+                    }
+                }
+            }
+            if self.backward {
+                if let Some(infector) = network_properties.infected_by[node] {
+                    if self.rng.gen::<f64>() < self.trace_probability {
+                        self.pending.push((day + self.trace_delay, infector, level + 1));
+                    }
+                }
+            }
+        }
+        self.quarantined.push(network_properties.quarantine.iter().filter(|x| **x > 0).count());
+    }
+
+    fn after_step(&mut self, _network_structure: &mut NetworkStructure, network_properties: &mut NetworkProperties, day: usize) {
+        // symptom onsets of the day just simulated
+        let infectious: Vec<bool> = network_properties.nodal_states
+            .iter()
+            .map(|x| x.symptomatic())
+            .collect();
+        for (node, now) in infectious.iter().enumerate() {
+            if *now && !self.infectious.get(node).copied().unwrap_or(false) && self.rng.gen::<f64>() < self.detection_probability {
+                self.pending.push((day + 1 + self.detection_delay, node, 0));
+            }
+        }
+        self.infectious = infectious;
+    }
+
+    fn record(&self, output: &mut Output) {
+        output.quarantined = self.quarantined.clone();
+    }
+}
+
+pub fn run_model_with_tracing(network_structure: &NetworkStructure, network_properties: &mut NetworkProperties, maxtime: f64, initially_infected: f64, mut tracing: ContactTracing) -> Result<Output, SeedingError> {
+    // compares against a run without tracing from the same seed, which shares the initial
+    // infections. The two runs use their random numbers differently as soon as anyone is traced,
+    // so the difference is one noisy sample, to be averaged over many seeds
+    let seed = network_properties.seed.unwrap_or_else(|| rand::thread_rng().gen());
+    network_properties.seed = Some(seed);
+    let mut untraced_properties = network_properties.clone();
+    let untraced = run_model(network_structure, &mut untraced_properties, maxtime, initially_infected)?;
+    tracing.seed(seed);
+    let mut interventions: Vec<Box<dyn Intervention>> = vec![Box::new(tracing)];
+    let mut output = run_model_with_interventions(network_structure, network_properties, maxtime, initially_infected, &mut interventions)?;
+    // imported infections happen whether or not anyone is traced, so only transmissions count
+    let prevented = transmissions_on_network(&untraced).saturating_sub(transmissions_on_network(&output));
+    output.infections_prevented = isize::try_from(prevented).unwrap_or(isize::MAX);
+    Ok(output)
+}
+
+fn transmissions_on_network(output: &Output) -> usize {
+    output.transmissions
+        .iter()
+        .filter(|(_, source, _)| matches!(source, InfectionSource::Node(_)))
+        .count()
+}
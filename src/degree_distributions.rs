@@ -0,0 +1,195 @@
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use statrs::distribution::{ContinuousCDF, Discrete, Geometric, LogNormal, NegativeBinomial, Poisson};
+
+// Families that the number of contacts between two age brackets can be drawn from.
+// In a params json each block names its family, e.g. {"family": "poisson", "lambda": 2.0}.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "family", rename_all = "snake_case")]
+pub enum DegreeDistribution {
+    Poisson { lambda: f64 },
+    // support starts at 1, as sampled by statrs
+    Geometric { p: f64 },
+    // number of failures before r successes
+    NegativeBinomial { r: f64, p: f64 },
+    // poisson with probability p, otherwise geometric, as fitted in fitting_parameters*.json
+    PoissonGeometric { lambda: f64, p_geom: f64, p: f64 },
+    // extra zeros with probability pi on top of any other family
+    ZeroInflated { pi: f64, base: Box<DegreeDistribution> },
+    // lognormal rounded down to whole contacts
+    LogNormal { mu: f64, sigma: f64 },
+    // probability of each degree from 0 upwards
+    Empirical { pmf: Vec<f64> },
+}
+
+impl DegreeDistribution {
+
+    pub fn pmf(&self, k: usize) -> f64 {
+        match self {
+            DegreeDistribution::Poisson { lambda } => poisson_pmf(*lambda, k),
+            DegreeDistribution::Geometric { p } => Geometric::new(*p).unwrap().pmf(k as u64),
+            DegreeDistribution::NegativeBinomial { r, p } => NegativeBinomial::new(*r, *p).unwrap().pmf(k as u64),
+            DegreeDistribution::PoissonGeometric { lambda, p_geom, p } => {
+                p*poisson_pmf(*lambda, k) + (1.0-p)*Geometric::new(*p_geom).unwrap().pmf(k as u64)
+            },
+            DegreeDistribution::ZeroInflated { pi, base } => {
+                let zero = if k == 0 { *pi } else { 0.0 };
+                zero + (1.0-pi)*base.pmf(k)
+            },
+            DegreeDistribution::LogNormal { mu, sigma } => {
+                let lognormal = LogNormal::new(*mu, *sigma).unwrap();
+                lognormal.cdf((k + 1) as f64) - lognormal.cdf(k as f64)
+            },
+            DegreeDistribution::Empirical { pmf } => {
+                let total: f64 = pmf.iter().sum();
+                pmf.get(k).map_or(0.0, |x| x / total)
+            }
+        }
+    }
+
+    pub fn pmf_vec(&self) -> Vec<f64> {
+        // probabilities from 0 until the remaining tail mass is negligible
+        if let DegreeDistribution::Empirical { pmf } = self {
+            return (0..pmf.len()).map(|k| self.pmf(k)).collect()
+        }
+        let mut pmf: Vec<f64> = Vec::new();
+        let mut total: f64 = 0.0;
+        while total < 1.0 - 1e-12 && pmf.len() < 100_000 {
+            let mass = self.pmf(pmf.len());
+            pmf.push(mass);
+            total += mass;
+        }
+        pmf
+    }
+
+    pub fn cdf_vec(&self) -> Vec<f64> {
+        self.pmf_vec()
+            .iter()
+            .scan(0.0, |total, x| {
+                *total += x;
+                Some(*total)
+            })
+            .collect()
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.pmf_vec()
+            .iter()
+            .enumerate()
+            .map(|(k, p)| (k as f64)*p)
+            .sum()
+    }
+
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> usize {
+        match self {
+            DegreeDistribution::Poisson { lambda } => {
+                if *lambda > 0.0 { Poisson::new(*lambda).unwrap().sample(rng) as usize } else { 0 }
+            },
+            DegreeDistribution::Geometric { p } => Geometric::new(*p).unwrap().sample(rng) as usize,
+            DegreeDistribution::NegativeBinomial { r, p } => NegativeBinomial::new(*r, *p).unwrap().sample(rng) as usize,
+            DegreeDistribution::PoissonGeometric { lambda, p_geom, p } => {
+                // pick the mixture component first, then draw from it
+                if rng.gen::<f64>() < *p {
+                    DegreeDistribution::Poisson { lambda: *lambda }.sample(rng)
+                } else {
+                    DegreeDistribution::Geometric { p: *p_geom }.sample(rng)
+                }
+            },
+            DegreeDistribution::ZeroInflated { pi, base } => {
+                if rng.gen::<f64>() < *pi { 0 } else { base.sample(rng) }
+            },
+            DegreeDistribution::LogNormal { mu, sigma } => {
+                LogNormal::new(*mu, *sigma).unwrap().sample(rng).floor() as usize
+            },
+            DegreeDistribution::Empirical { pmf } => {
+                // a single draw, use a sampler for many
+                let mut u = rng.gen::<f64>() * pmf.iter().sum::<f64>();
+                pmf.iter().position(|x| { u -= x; u < 0.0 }).unwrap_or(pmf.len().saturating_sub(1))
+            }
+        }
+    }
+
+    pub fn sampler(&self) -> Result<DegreeSampler, Box<dyn Error>> {
+        // checks the parameters once and works out anything shared between draws
+        // statrs reports bad parameters when a distribution is built, rather than when it is drawn from
+        match self {
+            DegreeDistribution::Poisson { lambda } => check_rate(*lambda)?,
+            DegreeDistribution::Geometric { p } => { Geometric::new(*p)?; },
+            DegreeDistribution::NegativeBinomial { r, p } => { NegativeBinomial::new(*r, *p)?; },
+            DegreeDistribution::PoissonGeometric { lambda, p_geom, p } => {
+                check_rate(*lambda)?;
+                Geometric::new(*p_geom)?;
+                check_probability(*p)?;
+            },
+            DegreeDistribution::ZeroInflated { pi, .. } => check_probability(*pi)?,
+            DegreeDistribution::LogNormal { mu, sigma } => { LogNormal::new(*mu, *sigma)?; },
+            DegreeDistribution::Empirical { .. } => ()
+        }
+        match self {
+            DegreeDistribution::ZeroInflated { pi, base } => {
+                Ok(DegreeSampler::ZeroInflated { pi: *pi, base: Box::new(base.sampler()?) })
+            },
+            DegreeDistribution::Empirical { pmf } => {
+                if pmf.iter().any(|x| !x.is_finite() || *x < 0.0) {
+                    return Err("empirical pmf has a negative or missing probability".into())
+                }
+                if pmf.iter().sum::<f64>() <= 0.0 {
+                    return Err("empirical pmf has no probability mass".into())
+                }
+                Ok(DegreeSampler::Table(self.cdf_vec()))
+            },
+            _ => Ok(DegreeSampler::Direct(self.clone()))
+        }
+    }
+}
+
+// A distribution ready to draw the degrees of every node in a block, with the cumulative
+// probabilities of an empirical pmf worked out once rather than on every draw.
+#[derive(Clone, Debug)]
+pub enum DegreeSampler {
+    Direct(DegreeDistribution),
+    ZeroInflated { pi: f64, base: Box<DegreeSampler> },
+    Table(Vec<f64>)
+}
+
+impl DegreeSampler {
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> usize {
+        match self {
+            DegreeSampler::Direct(distribution) => distribution.sample(rng),
+            DegreeSampler::ZeroInflated { pi, base } => {
+                if rng.gen::<f64>() < *pi { 0 } else { base.sample(rng) }
+            },
+            DegreeSampler::Table(cdf) => {
+                let u: f64 = rng.gen();
+                cdf.partition_point(|&x| x < u).min(cdf.len() - 1)
+            }
+        }
+    }
+}
+
+fn poisson_pmf(lambda: f64, k: usize) -> f64 {
+    // statrs rejects a zero rate, which is a point mass at zero
+    if lambda > 0.0 {
+        Poisson::new(lambda).unwrap().pmf(k as u64)
+    } else if k == 0 {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+fn check_rate(lambda: f64) -> Result<(), Box<dyn Error>> {
+    // a zero rate is allowed, as a point mass at zero
+    if lambda != 0.0 {
+        Poisson::new(lambda)?;
+    }
+    Ok(())
+}
+
+fn check_probability(p: f64) -> Result<(), Box<dyn Error>> {
+    if !(0.0..=1.0).contains(&p) {
+        return Err(format!("probability {} is not between 0 and 1", p).into())
+    }
+    Ok(())
+}
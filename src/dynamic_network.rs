@@ -0,0 +1,186 @@
+use crate::random_graphs::*;
+use crate::seeding::SeedingError;
+use crate::run_model::{run_days, step_model};
+use crate::useful_functions::{group_sizes, rates_to_probabilities, DistributionParameters};
+use crate::degree_distributions::DegreeSampler;
+use nalgebra_sparse::coo::CooMatrix;
+use rand::prelude::*;
+use rand_distr::{Binomial, Poisson};
+use std::collections::HashSet;
+use std::error::Error;
+
+// Contact surveys such as CoMix measure contacts on a single day, and most are not repeated.
+// A DynamicNetwork keeps a fixed backbone of repeated contacts and redraws the rest every day.
+
+pub enum TransientContacts {
+    // degrees from the distributions of each pair of age brackets, paired by random stub matching
+    Configuration(DistributionParameters),
+    // poisson number of edges for each pair of age brackets, with uniformly chosen ends
+    Sbm(Vec<Vec<f64>>)
+}
+
+pub struct DynamicNetwork {
+    pub backbone: NetworkStructure,
+    pub transient: TransientContacts,
+    pub partitions: Vec<usize>,
+    // share of the sampled contacts that are redrawn daily, the rest are assumed to be in the backbone
+    pub transient_fraction: f64,
+    // backbone with the contacts of the current day, which follow the backbone entries
+    pub today: NetworkStructure,
+    // degree samplers of the configuration contacts, built once rather than every day
+    pub samplers: Vec<Vec<DegreeSampler>>
+}
+
+impl DynamicNetwork {
+
+    pub fn new(backbone: NetworkStructure, partitions: Vec<usize>, transient: TransientContacts) -> Result<DynamicNetwork, Box<dyn Error>> {
+        // fixed contacts such as a household network, with all sampled contacts redrawn daily.
+        // The contacts of a day are drawn by the runner, so today starts as the backbone
+        DynamicNetwork::from_parts(backbone, partitions, transient, 1.0)
+    }
+
+    pub fn with_persistent_fraction<R: Rng>(n: usize, partitions: Vec<usize>, transient: TransientContacts, persistent_fraction: f64, rng: &mut R) -> Result<DynamicNetwork, Box<dyn Error>> {
+        // a first draw of the contacts with each edge kept in the backbone with probability
+        // persistent_fraction, the remaining share of everyone's degree is redrawn each day
+        let empty = NetworkStructure::new_empty(n, &partitions);
+        let mut full = DynamicNetwork::from_parts(empty, partitions.clone(), transient, 1.0)?;
+        full.redraw(rng);
+        let mut backbone = NetworkStructure::new_empty(n, &partitions);
+        for (i, j, weight) in full.today.adjacency_matrix.triplet_iter() {
+            if i > j && rng.gen::<f64>() < persistent_fraction {
+                backbone.push_edge(i, j, *weight, 0);
+            }
+        }
+        DynamicNetwork::from_parts(backbone, partitions, full.transient, 1.0 - persistent_fraction)
+    }
+
+    fn from_parts(mut backbone: NetworkStructure, partitions: Vec<usize>, transient: TransientContacts, transient_fraction: f64) -> Result<DynamicNetwork, Box<dyn Error>> {
+        // daily contacts get a layer of their own, so they can be weighted separately
+        backbone.layer_id("transient");
+        let samplers = match &transient {
+            TransientContacts::Configuration(dist_params) => dist_params.samplers()?,
+            TransientContacts::Sbm(_) => Vec::new()
+        };
+        Ok(DynamicNetwork {
+            today: backbone.clone(),
+            backbone,
+            transient,
+            partitions,
+            transient_fraction,
+            samplers
+        })
+    }
+
+    pub fn redraw<R: Rng>(&mut self, rng: &mut R) {
+        // only the transient contacts are generated, in time linear in their number
+        let edges = match &self.transient {
+            TransientContacts::Configuration(_) => self.configuration_edges(rng),
+            TransientContacts::Sbm(rates_mat) => self.sbm_edges(rates_mat, rng)
+        };
+        // the backbone entries come first, so yesterday's contacts are the ones after them
+        self.today.truncate_edges(self.backbone.adjacency_matrix.nnz());
+        let layer = self.today.layer_id("transient");
+        // in a fixed order, so a seeded run does not depend on the hash set's order
+        let mut edges: Vec<(usize, usize)> = edges.into_iter().collect();
+        edges.sort_unstable();
+        for (i, j) in edges.into_iter() {
+            self.today.push_edge(i, j, 1.0, layer);
+        }
+    }
+
+    fn configuration_edges<R: Rng>(&self, rng: &mut R) -> HashSet<(usize, usize)> {
+        let group_sizes = group_sizes(&self.partitions);
+        let mut edges: HashSet<(usize, usize)> = HashSet::new();
+        // stubs of bracket i towards bracket j, each kept with the transient fraction
+        let stubs = |i: usize, j: usize, rng: &mut R| -> Vec<usize> {
+            let start = self.partitions[i] - group_sizes[i];
+            let mut stubs: Vec<usize> = Vec::new();
+            for node in start..self.partitions[i] {
+                let degree = self.samplers[i][j].sample(rng) as u64;
+                let kept = match Binomial::new(degree, self.transient_fraction.clamp(0.0, 1.0)) {
+                    Ok(dist) => dist.sample(rng) as usize,
+                    Err(_) => 0
+                };
+                stubs.extend(std::iter::repeat_n(node, kept));
+            }
+            stubs.shuffle(rng);
+            stubs
+        };
+        for i in 0..self.partitions.len() {
+            for j in 0..=i {
+                // within a bracket the stubs are paired among themselves
+                let (out_stubs, in_stubs) = if i == j {
+                    let stubs = stubs(i, i, rng);
+                    let half = stubs.len() / 2;
+                    (stubs[..half].to_vec(), stubs[half..].to_vec())
+                } else {
+                    (stubs(i, j, rng), stubs(j, i, rng))
+                };
+                // unmatched stubs are dropped, as are self loops and repeated pairs
+                for (&a, &b) in out_stubs.iter().zip(in_stubs.iter()) {
+                    if a != b {
+                        edges.insert((a.max(b), a.min(b)));
+                    }
+                }
+            }
+        }
+        edges
+    }
+
+    fn sbm_edges<R: Rng>(&self, rates_mat: &[Vec<f64>], rng: &mut R) -> HashSet<(usize, usize)> {
+        let prob_mat = rates_to_probabilities(rates_mat.to_vec(), &self.partitions);
+        let group_sizes = group_sizes(&self.partitions);
+        let mut edges: HashSet<(usize, usize)> = HashSet::new();
+        for (i, x) in self.partitions.iter().enumerate() {
+            for (j, y) in self.partitions.iter().enumerate().take(i+1) {
+                let (start_i, start_j) = (*x - group_sizes[i], *y - group_sizes[j]);
+                let pairs = if i == j {
+                    (group_sizes[i] as f64) * (group_sizes[i] as f64 - 1.0) / 2.0
+                } else {
+                    (group_sizes[i] as f64) * (group_sizes[j] as f64)
+                };
+                let expected_edges = pairs * prob_mat[i][j] * self.transient_fraction;
+                if expected_edges <= 0.0 {
+                    continue;
+                }
+                let num_edges = Poisson::new(expected_edges).unwrap().sample(rng) as usize;
+                for _ in 0..num_edges {
+                    let node_i = rng.gen_range(start_i..*x);
+                    let node_j = rng.gen_range(start_j..*y);
+                    if node_i != node_j {
+                        edges.insert((node_i.max(node_j), node_i.min(node_j)));
+                    }
+                }
+            }
+        }
+        edges
+    }
+}
+
+impl NetworkStructure {
+
+    pub fn new_empty(n: usize, partitions: &[usize]) -> NetworkStructure {
+        // nodes and age brackets without any contacts
+        let group_sizes = group_sizes(partitions);
+        NetworkStructure {
+            adjacency_matrix: CooMatrix::new(n,n),
+            degree: vec![0.0; n],
+            age_brackets: group_sizes
+                .iter()
+                .enumerate()
+                .flat_map(|(i, size)| vec![i; *size])
+                .collect(),
+            household: Vec::new(),
+            layers: Vec::new(),
+            edge_layer: Vec::new()
+        }
+    }
+}
+
+pub fn run_model_dynamic(dynamic_network: &mut DynamicNetwork, network_properties: &mut NetworkProperties, maxtime: f64, initially_infected: f64) -> Result<Output, SeedingError> {
+    // as run_model, with the transient contacts redrawn from the run's generator before every day
+    run_days(network_properties, maxtime, initially_infected, |network_properties, _, rng| {
+        dynamic_network.redraw(rng);
+        step_model(&dynamic_network.today, network_properties, rng)
+    })
+}
@@ -0,0 +1,207 @@
+use crate::random_graphs::Output;
+use crate::useful_functions::*;
+use crate::write_to_file::read_params_json;
+use crate::ode_solver::*;
+
+// Edge-based compartmental model (Miller, Slim & Volz 2012) for SEIR dynamics on a
+// configuration network with age blocks. A node in block a has an independent number
+// of contacts with each block b, drawn from the fitted distribution for (a, b), which
+// is the same degree model sampled by new_molloy_reed.
+//
+// theta[a][b] is the probability that the contact of an a-node in block b has not yet
+// transmitted to it, and phi_i/phi_r are the probabilities that such a contact is
+// infectious/recovered and has not transmitted.
+
+pub struct EdgeBasedModel {
+    pub group_sizes: Vec<usize>,
+    pub degree_pmfs: Vec<Vec<Vec<f64>>>,
+    // per contact daily transmission rate, latent and recovery rates
+    pub beta: f64,
+    pub sigma: f64,
+    pub gamma: f64,
+    pub initially_infected: f64,
+    // relative susceptibility and infectiousness of each block
+    pub susceptibility: Vec<f64>,
+    pub infectiousness: Vec<f64>
+}
+
+impl EdgeBasedModel {
+
+    pub fn new(partitions: &[usize], dist_params: &DistributionParameters, parameters: &[f64], initially_infected: f64) -> EdgeBasedModel {
+        let blocks = partitions.len();
+        let degree_pmfs: Vec<Vec<Vec<f64>>> = (0..blocks)
+            .map(|i| {
+                (0..blocks).map(|j| dist_params.degree_pmf(i, j)).collect()
+            })
+            .collect();
+        // the stochastic model transmits with probability parameters[0] each day and keeps
+        // nodes exposed/infected for a poisson number of days plus the day of transition
+        EdgeBasedModel {
+            group_sizes: group_sizes(partitions),
+            degree_pmfs,
+            beta: -(1.0 - parameters[0]).ln(),
+            sigma: 1.0 / (parameters[1] + 1.0),
+            gamma: 1.0 / (parameters[2] + 1.0),
+            initially_infected,
+            susceptibility: vec![1.0; blocks],
+            infectiousness: vec![1.0; blocks]
+        }
+    }
+
+    pub fn with_multipliers(mut self, susceptibility: Vec<f64>, infectiousness: Vec<f64>) -> EdgeBasedModel {
+        // individual variation enters through the block means, which ignores its effect on
+        // who is infected first
+        self.susceptibility = susceptibility;
+        self.infectiousness = infectiousness;
+        self
+    }
+
+    fn rate(&self, to: usize, from: usize) -> f64 {
+        // transmission rate along a contact from a node in block from to one in block to
+        self.beta * self.susceptibility[to] * self.infectiousness[from]
+    }
+
+    pub fn from_file(partitions: &[usize], file_path: &str, parameters: &[f64], initially_infected: f64) -> EdgeBasedModel {
+        let dist_params = read_params_json(file_path);
+        EdgeBasedModel::new(partitions, &dist_params, parameters, initially_infected)
+    }
+
+    pub fn initial_conditions(&self) -> Vec<f64> {
+        let blocks = self.group_sizes.len();
+        let rho = self.initially_infected;
+        // theta, phi_i and phi_r for each block pair followed by i and r for each block
+        let mut y: Vec<f64> = vec![0.0; 3*blocks*blocks + 2*blocks];
+        for ab in 0..blocks*blocks {
+            y[ab] = 1.0;
+            y[blocks*blocks + ab] = rho;
+        }
+        for a in 0..blocks {
+            y[3*blocks*blocks + a] = rho;
+        }
+        y
+    }
+
+    pub fn derivatives(&self, y: &[f64]) -> Vec<f64> {
+        let blocks = self.group_sizes.len();
+        let b2 = blocks*blocks;
+        let (theta, rest) = y.split_at(b2);
+        let (phi_i, rest) = rest.split_at(b2);
+        let (phi_r, rest) = rest.split_at(b2);
+        let (infected, recovered) = rest.split_at(blocks);
+
+        let mut dy: Vec<f64> = vec![0.0; y.len()];
+        for a in 0..blocks {
+            for b in 0..blocks {
+                let ab = a*blocks + b;
+                let phi_e = theta[ab] - self.phi_s(theta, a, b) - phi_i[ab] - phi_r[ab];
+                let rate = self.rate(a, b);
+                dy[ab] = -rate * phi_i[ab];
+                dy[b2 + ab] = self.sigma * phi_e - (rate + self.gamma) * phi_i[ab];
+                dy[2*b2 + ab] = self.gamma * phi_i[ab];
+            }
+            let exposed = 1.0 - self.susceptible(theta, a) - infected[a] - recovered[a];
+            dy[3*b2 + a] = self.sigma * exposed - self.gamma * infected[a];
+            dy[3*b2 + blocks + a] = self.gamma * infected[a];
+        }
+        dy
+    }
+
+    pub fn susceptible(&self, theta: &[f64], a: usize) -> f64 {
+        // a node escapes infection if none of its contacts in any block have transmitted
+        let blocks = self.group_sizes.len();
+        (1.0 - self.initially_infected) * (0..blocks)
+            .map(|b| pgf(&self.degree_pmfs[a][b], theta[a*blocks + b]))
+            .product::<f64>()
+    }
+
+    fn phi_s(&self, theta: &[f64], a: usize, b: usize) -> f64 {
+        // the b-neighbour is susceptible if its other contacts have not transmitted,
+        // counting its remaining edges back to block a by the excess degree
+        let blocks = self.group_sizes.len();
+        let pmf_ba = &self.degree_pmfs[b][a];
+        let mean_ba = pgf_derivative(pmf_ba, 1.0);
+        let back = if mean_ba > 0.0 { pgf_derivative(pmf_ba, theta[b*blocks + a]) / mean_ba } else { 1.0 };
+        let others: f64 = (0..blocks)
+            .filter(|&c| c != a)
+            .map(|c| pgf(&self.degree_pmfs[b][c], theta[b*blocks + c]))
+            .product();
+        (1.0 - self.initially_infected) * back * others
+    }
+
+    pub fn seir(&self, y: &[f64]) -> Vec<f64> {
+        // compartment sizes summed over age blocks, in the same order as count_states
+        let blocks = self.group_sizes.len();
+        let b2 = blocks*blocks;
+        let mut result: Vec<f64> = vec![0.0; 4];
+        for (a, size) in self.group_sizes.iter().enumerate() {
+            let n_a = *size as f64;
+            let s = self.susceptible(&y[..b2], a);
+            let i = y[3*b2 + a];
+            let r = y[3*b2 + blocks + a];
+            result[0] += n_a * s;
+            result[1] += n_a * (1.0 - s - i - r);
+            result[2] += n_a * i;
+            result[3] += n_a * r;
+        }
+        result
+    }
+
+    pub fn r0(&self) -> f64 {
+        // next generation matrix over edge types (a -> b), infection reaching a b-node
+        // from block a spreads along its excess edges back to a and all edges to c != a
+        let blocks = self.group_sizes.len();
+        let transmissibility = |to: usize, from: usize| self.rate(to, from) / (self.rate(to, from) + self.gamma);
+        let mut ngm: Vec<Vec<f64>> = vec![vec![0.0; blocks*blocks]; blocks*blocks];
+        for a in 0..blocks {
+            for b in 0..blocks {
+                for c in 0..blocks {
+                    let pmf = &self.degree_pmfs[b][c];
+                    let mean = pgf_derivative(pmf, 1.0);
+                    let offspring = if c == a {
+                        if mean > 0.0 { pgf_second_derivative(pmf, 1.0) / mean } else { 0.0 }
+                    } else {
+                        mean
+                    };
+                    ngm[b*blocks + c][a*blocks + b] = transmissibility(c, b) * offspring;
+                }
+            }
+        }
+        spectral_radius(&ngm)
+    }
+}
+
+pub fn run_edge_based_model(model: &EdgeBasedModel, maxtime: f64, solver: OdeSolver) -> Output {
+    let trajectory = solve_daily(|_, y| model.derivatives(y), model.initial_conditions(), maxtime, solver);
+    let mut output: Output = Output::new();
+    output.seir = trajectory
+        .iter()
+        .map(|y| {
+            model.seir(y)
+                .iter()
+                .map(|x| x.round().max(0.0) as usize)
+                .collect()
+        })
+        .collect();
+    output
+}
+
+fn pgf(pmf: &[f64], x: f64) -> f64 {
+    // horner evaluation of sum p_k x^k
+    pmf.iter().rev().fold(0.0, |acc, p| acc*x + p)
+}
+
+fn pgf_derivative(pmf: &[f64], x: f64) -> f64 {
+    pmf.iter()
+        .enumerate()
+        .skip(1)
+        .rev()
+        .fold(0.0, |acc, (k, p)| acc*x + (k as f64)*p)
+}
+
+fn pgf_second_derivative(pmf: &[f64], x: f64) -> f64 {
+    pmf.iter()
+        .enumerate()
+        .skip(2)
+        .rev()
+        .fold(0.0, |acc, (k, p)| acc*x + ((k*(k-1)) as f64)*p)
+}
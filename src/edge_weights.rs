@@ -0,0 +1,122 @@
+use crate::random_graphs::{NetworkProperties, NetworkStructure, TransmissionMode};
+use nalgebra_sparse::coo::CooMatrix;
+use rand::prelude::*;
+use rand::distributions::WeightedIndex;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+// Edge weights are the values of the adjacency matrix. How they are read during transmission
+// depends on the TransmissionMode of the NetworkProperties: as a contact duration in units of
+// a standard day of contact, or as the probability that the contact happens on a given day.
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "family", rename_all = "snake_case")]
+pub enum ContactWeight {
+    // the same weight on every contact
+    Fixed { value: f64 },
+    Gamma { shape: f64, mean: f64 },
+    LogNormal { mu: f64, sigma: f64 },
+    // a set of weights with their probabilities, e.g. daily 1.0, weekly 1/7 and one-off contacts
+    Categorical { values: Vec<f64>, probabilities: Vec<f64> },
+}
+
+impl ContactWeight {
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> f64 {
+        match self {
+            ContactWeight::Fixed { value } => *value,
+            ContactWeight::Gamma { shape, mean } => rand_distr::Gamma::new(*shape, mean / shape).unwrap().sample(rng),
+            ContactWeight::LogNormal { mu, sigma } => rand_distr::LogNormal::new(*mu, *sigma).unwrap().sample(rng),
+            ContactWeight::Categorical { values, probabilities } => {
+                match WeightedIndex::new(probabilities) {
+                    Ok(dist) => values[dist.sample(rng)],
+                    Err(err) => {
+                        eprintln!("Error: {}, using a weight of one", err);
+                        1.0
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl NetworkStructure {
+
+    pub fn assign_edge_weights(&mut self, weights: &[Vec<ContactWeight>]) {
+        // weights[a][b] is the distribution for contacts between age brackets a and b
+        self.sample_edge_weights(weights, None);
+    }
+
+    pub fn assign_layer_weights(&mut self, name: &str, weights: &[Vec<ContactWeight>]) {
+        // only the edges of one layer, so home and brief community contacts can differ
+        match self.layers.iter().position(|x| x == name) {
+            Some(layer) => self.sample_edge_weights(weights, Some(layer)),
+            None => println!("There is no layer called {name}")
+        }
+    }
+
+    pub fn edge_weight(&self, edge: usize) -> f64 {
+        self.adjacency_matrix.values()[edge]
+    }
+
+    fn sample_edge_weights(&mut self, weights: &[Vec<ContactWeight>], layer: Option<usize>) {
+        let mut rng: ThreadRng = rand::thread_rng();
+        let n = self.degree.len();
+        // both directions of a contact share one weight, drawn from the lower triangle block
+        let mut sampled: HashMap<(usize, usize), f64> = HashMap::new();
+        let mut coo_mat: CooMatrix<f64> = CooMatrix::new(n,n);
+        // networks without age brackets, e.g. new_ba, are treated as a single bracket
+        let bracket = |node: usize| self.age_brackets.get(node).copied().unwrap_or(0);
+        for (edge, (i, j, weight)) in self.adjacency_matrix.triplet_iter().enumerate() {
+            if layer.is_some_and(|x| x != self.edge_layer(edge)) {
+                coo_mat.push(i, j, *weight);
+                continue;
+            }
+            let (a, b) = (bracket(i), bracket(j));
+            let weight = *sampled
+                .entry((i.max(j), i.min(j)))
+                .or_insert_with(|| {
+                    weights
+                        .get(a.max(b))
+                        .and_then(|row| row.get(a.min(b)))
+                        .map_or(1.0, |dist| dist.sample(&mut rng))
+                });
+            coo_mat.push(i, j, weight);
+        }
+        // rebuilt in the same order, so the layer of each entry is unchanged
+        self.adjacency_matrix = coo_mat;
+    }
+}
+
+impl NetworkProperties {
+
+    pub fn transmission_probability(&self, network_structure: &NetworkStructure, i: usize, j: usize, edge: usize) -> f64 {
+        // daily probability of transmission from i to j along one entry of the adjacency matrix
+        if self.quarantined(i) || self.quarantined(j) {
+            return 0.0
+        }
+        let multiplier = self.layer_multiplier(network_structure.edge_layer(edge))
+            * self.edge_multipliers.get(edge).copied().unwrap_or(1.0)
+            * self.node_multiplier(i)
+            * self.node_multiplier(j)
+            * self.infectiousness(network_structure, i)
+            * self.susceptibility(network_structure, j)
+            * (1.0 - self.vaccine_status.get(i).map_or(0.0, |x| x.transmission_protection))
+            * (1.0 - self.vaccine_status.get(j).map_or(0.0, |x| x.infection_protection));
+        let p = (self.parameters[0] * multiplier).min(1.0);
+        match self.transmission_mode {
+            TransmissionMode::Unweighted => p,
+            TransmissionMode::Duration => 1.0 - (1.0 - p).powf(network_structure.edge_weight(edge)),
+            // the contact happens with probability given by the weight, then transmits with p,
+            // which are independent so one draw on the product is enough
+            TransmissionMode::Frequency => network_structure.edge_weight(edge).clamp(0.0, 1.0) * p
+        }
+    }
+
+    pub fn node_multiplier(&self, node: usize) -> f64 {
+        self.node_multipliers.get(node).copied().unwrap_or(1.0)
+    }
+
+    pub fn quarantined(&self, node: usize) -> bool {
+        self.quarantine.get(node).is_some_and(|x| *x > 0)
+    }
+}
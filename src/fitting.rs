@@ -0,0 +1,202 @@
+use crate::useful_functions::DistributionParameters;
+use crate::degree_distributions::DegreeDistribution;
+use crate::write_to_file::{read_contact_records, read_participant_records, results_json};
+use serde::{Deserialize, Serialize};
+use statrs::distribution::{ChiSquared, ContinuousCDF};
+use std::collections::HashMap;
+
+// Fitting of the poisson-geometric contact distributions in fitting_parameters*.json
+// from participant level survey data, by expectation maximisation on each age pair.
+
+#[derive(Debug, Deserialize)]
+pub struct ContactRecord {
+    pub participant_id: String,
+    pub participant_age: f64,
+    pub contact_age: f64,
+    pub count: usize
+}
+
+// Every participant surveyed, including those who reported no contacts and so have no
+// ContactRecord, as in the separate participant table of POLYMOD style surveys.
+#[derive(Debug, Deserialize)]
+pub struct ParticipantRecord {
+    pub participant_id: String,
+    pub participant_age: f64
+}
+
+#[derive(Debug, Serialize)]
+pub struct FitReport {
+    pub participant_bracket: usize,
+    pub contact_bracket: usize,
+    pub participants: usize,
+    pub log_likelihood: f64,
+    pub aic: f64,
+    pub chi_squared: f64,
+    pub degrees_of_freedom: usize,
+    pub p_value: f64
+}
+
+pub fn contact_counts(records: &[ContactRecord], participant_records: &[ParticipantRecord], age_limits: &[f64]) -> Vec<Vec<Vec<usize>>> {
+    // total contacts of every participant with each bracket, where a participant without
+    // any record for a bracket had no contacts in it. Participants only found in the contact
+    // records are counted too
+    let blocks = age_limits.len();
+    let mut participants: HashMap<&str, (usize, Vec<usize>)> = HashMap::new();
+    for participant in participant_records.iter() {
+        participants.insert(participant.participant_id.as_str(), (age_bracket(participant.participant_age, age_limits), vec![0; blocks]));
+    }
+    for record in records.iter() {
+        let entry = participants
+            .entry(record.participant_id.as_str())
+            .or_insert((age_bracket(record.participant_age, age_limits), vec![0; blocks]));
+        entry.1[age_bracket(record.contact_age, age_limits)] += record.count;
+    }
+    let mut counts: Vec<Vec<Vec<usize>>> = vec![vec![Vec::new(); blocks]; blocks];
+    for (bracket, contacts) in participants.values() {
+        for (j, count) in contacts.iter().enumerate() {
+            counts[*bracket][j].push(*count);
+        }
+    }
+    counts
+}
+
+pub fn fit_distribution_parameters(records: &[ContactRecord], participants: &[ParticipantRecord], age_limits: &[f64]) -> (DistributionParameters, Vec<FitReport>) {
+    let counts = contact_counts(records, participants, age_limits);
+    let blocks = age_limits.len();
+    let mut dist_params = DistributionParameters::new();
+    dist_params.lambda = vec![vec![0.0; blocks]; blocks];
+    dist_params.p_geom = vec![vec![1.0; blocks]; blocks];
+    // brackets without participants are left with no contacts
+    dist_params.p = vec![vec![1.0; blocks]; blocks];
+    let mut reports: Vec<FitReport> = Vec::new();
+    for (i, row) in counts.iter().enumerate() {
+        for (j, block_counts) in row.iter().enumerate() {
+            if block_counts.is_empty() {
+                println!("No participants in age bracket {i}, assuming no contacts");
+                continue;
+            }
+            let (lambda, p_geom, p) = fit_poisson_geometric(block_counts);
+            dist_params.lambda[i][j] = lambda;
+            dist_params.p_geom[i][j] = p_geom;
+            dist_params.p[i][j] = p;
+            reports.push(goodness_of_fit(block_counts, &dist_params.distribution(i, j), i, j));
+        }
+    }
+    (dist_params, reports)
+}
+
+pub fn fit_poisson_geometric(counts: &[usize]) -> (f64, f64, f64) {
+    // EM for p*Poisson(lambda) + (1-p)*Geometric(p_geom), with the geometric on 1, 2, ...
+    let n = counts.len() as f64;
+    let mean = counts.iter().sum::<usize>() as f64 / n;
+    let (mut lambda, mut p_geom, mut p) = (mean.max(1e-3), 1.0 / mean.max(1.0), 0.5);
+    let mut last_likelihood = f64::NEG_INFINITY;
+    for _ in 0..1000 {
+        // E step, responsibility of the poisson component for each count
+        let responsibilities: Vec<f64> = counts
+            .iter()
+            .map(|&k| {
+                let poisson = p*DegreeDistribution::Poisson { lambda }.pmf(k);
+                let geometric = (1.0-p)*DegreeDistribution::Geometric { p: p_geom }.pmf(k);
+                if poisson + geometric > 0.0 { poisson / (poisson + geometric) } else { 1.0 }
+            })
+            .collect();
+        // M step, weighted maximum likelihood for each component
+        let poisson_weight: f64 = responsibilities.iter().sum();
+        let geometric_weight: f64 = n - poisson_weight;
+        p = poisson_weight / n;
+        if poisson_weight > 0.0 {
+            lambda = (counts.iter().zip(responsibilities.iter()).map(|(&k, r)| (k as f64)*r).sum::<f64>() / poisson_weight).max(1e-6);
+        }
+        let geometric_total: f64 = counts.iter().zip(responsibilities.iter()).map(|(&k, r)| (k as f64)*(1.0-r)).sum();
+        if geometric_weight > 1e-12 && geometric_total > 0.0 {
+            p_geom = (geometric_weight / geometric_total).min(1.0);
+        }
+        let likelihood = log_likelihood(counts, &DegreeDistribution::PoissonGeometric { lambda, p_geom, p });
+        if (likelihood - last_likelihood).abs() < 1e-9 {
+            break;
+        }
+        last_likelihood = likelihood;
+    }
+    (lambda, p_geom, p)
+}
+
+pub fn log_likelihood(counts: &[usize], distribution: &DegreeDistribution) -> f64 {
+    counts.iter().map(|&k| distribution.pmf(k).max(f64::MIN_POSITIVE).ln()).sum()
+}
+
+pub fn goodness_of_fit(counts: &[usize], distribution: &DegreeDistribution, i: usize, j: usize) -> FitReport {
+    let n = counts.len() as f64;
+    let log_likelihood = log_likelihood(counts, distribution);
+    // pearson chi squared, pooling the tail so every bin expects at least 5 participants
+    let max_count = *counts.iter().max().unwrap();
+    let mut observed: Vec<f64> = Vec::new();
+    let mut expected: Vec<f64> = Vec::new();
+    let (mut bin_observed, mut bin_expected) = (0.0, 0.0);
+    let mut cumulative = 0.0;
+    for k in 0..=max_count {
+        let mass = distribution.pmf(k);
+        cumulative += mass;
+        bin_observed += counts.iter().filter(|&&x| x == k).count() as f64;
+        bin_expected += n*mass;
+        if bin_expected >= 5.0 && n*(1.0 - cumulative) >= 5.0 {
+            observed.push(bin_observed);
+            expected.push(bin_expected);
+            bin_observed = 0.0;
+            bin_expected = 0.0;
+        }
+    }
+    // remaining counts and tail mass go into the last bin
+    observed.push(bin_observed);
+    expected.push(bin_expected + n*(1.0 - cumulative).max(0.0));
+    let chi_squared: f64 = observed
+        .iter()
+        .zip(expected.iter())
+        .filter(|(_, e)| **e > 0.0)
+        .map(|(o, e)| (o - e).powi(2) / e)
+        .sum();
+    // three fitted parameters
+    let degrees_of_freedom = observed.len().saturating_sub(4).max(1);
+    let p_value = 1.0 - ChiSquared::new(degrees_of_freedom as f64).unwrap().cdf(chi_squared);
+    FitReport {
+        participant_bracket: i,
+        contact_bracket: j,
+        participants: counts.len(),
+        log_likelihood,
+        aic: 6.0 - 2.0*log_likelihood,
+        chi_squared,
+        degrees_of_freedom,
+        p_value
+    }
+}
+
+pub fn fit_survey_to_json(survey_path: &str, participants_path: Option<&str>, age_limits: &[f64], output_path: &str) -> Vec<FitReport> {
+    // survey csv with participant_id, participant_age, contact_age, count columns, and
+    // optionally a csv of participant_id, participant_age for everyone surveyed. Without it,
+    // participants who reported no contacts at all are missing from the fit
+    let records = match read_contact_records(survey_path) {
+        Ok(records) => records,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            return Vec::new()
+        }
+    };
+    let participants = match participants_path.map(read_participant_records) {
+        Some(Ok(participants)) => participants,
+        Some(Err(err)) => {
+            eprintln!("Error: {}", err);
+            return Vec::new()
+        },
+        None => Vec::new()
+    };
+    let (dist_params, reports) = fit_distribution_parameters(&records, &participants, age_limits);
+    if let Err(err) = results_json(&dist_params, output_path) {
+        eprintln!("Error: {}", err);
+    }
+    reports
+}
+
+fn age_bracket(age: f64, age_limits: &[f64]) -> usize {
+    // age_limits holds the lower age of each bracket in increasing order
+    age_limits.partition_point(|&x| x <= age).max(1) - 1
+}
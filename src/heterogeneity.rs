@@ -0,0 +1,53 @@
+use crate::random_graphs::*;
+use rand::prelude::*;
+use rand_distr::Gamma;
+
+// Differences in susceptibility and infectiousness, by age bracket and between individuals.
+// Transmission from i to j is scaled by the infectiousness of i and the susceptibility of j.
+
+impl NetworkProperties {
+
+    pub fn draw_heterogeneity(&mut self, susceptibility_shape: Option<f64>, infectiousness_shape: Option<f64>) {
+        // individual multipliers from gamma distributions with mean one, a smaller shape gives
+        // more variation. None leaves every node the same
+        let n = self.nodal_states.len();
+        // a different stream to the one that picks the initial cases
+        let mut rng = self.rng_stream(1);
+        let mut draw = |shape: Option<f64>| -> Vec<f64> {
+            match shape {
+                Some(k) => {
+                    let gamma = Gamma::new(k, 1.0 / k).unwrap();
+                    (0..n).map(|_| gamma.sample(&mut rng)).collect()
+                },
+                None => Vec::new()
+            }
+        };
+        self.node_susceptibility = draw(susceptibility_shape);
+        self.node_infectiousness = draw(infectiousness_shape);
+    }
+
+    pub fn susceptibility(&self, network_structure: &NetworkStructure, node: usize) -> f64 {
+        // networks without age brackets, e.g. new_ba, only have the individual multipliers
+        network_structure.age_brackets.get(node).and_then(|a| self.age_susceptibility.get(*a)).copied().unwrap_or(1.0)
+            * self.node_susceptibility.get(node).copied().unwrap_or(1.0)
+    }
+
+    pub fn infectiousness(&self, network_structure: &NetworkStructure, node: usize) -> f64 {
+        network_structure.age_brackets.get(node).and_then(|a| self.age_infectiousness.get(*a)).copied().unwrap_or(1.0)
+            * self.node_infectiousness.get(node).copied().unwrap_or(1.0)
+    }
+
+    pub fn block_multipliers(&self, partitions: &[usize]) -> (Vec<f64>, Vec<f64>) {
+        // mean susceptibility and infectiousness of each age bracket, for the analytical models,
+        // with the nodes of each bracket numbered consecutively as in new_sbm and new_molloy_reed
+        let mean = |age: &Vec<f64>, node: &Vec<f64>, block: usize| -> f64 {
+            let start = if block == 0 { 0 } else { partitions[block - 1] };
+            let values: Vec<f64> = (start..partitions[block]).filter_map(|i| node.get(i).copied()).collect();
+            let individual = if values.is_empty() { 1.0 } else { values.iter().sum::<f64>() / values.len() as f64 };
+            age.get(block).copied().unwrap_or(1.0) * individual
+        };
+        (0..partitions.len())
+            .map(|block| (mean(&self.age_susceptibility, &self.node_susceptibility, block), mean(&self.age_infectiousness, &self.node_infectiousness, block)))
+            .unzip()
+    }
+}
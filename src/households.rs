@@ -0,0 +1,139 @@
+use crate::random_graphs::NetworkStructure;
+use crate::write_to_file::read_household_json;
+use nalgebra_sparse::coo::CooMatrix;
+use rand::prelude::*;
+use rand::distributions::WeightedIndex;
+use serde::Deserialize;
+use std::collections::HashSet;
+
+#[derive(Debug, Deserialize)]
+pub struct HouseholdParameters {
+    // probability of each household size, starting from a single person
+    pub size_distribution: Vec<f64>,
+    // for each household size, the probability that a member is in each age bracket
+    pub age_composition: Vec<Vec<f64>>
+}
+
+impl HouseholdParameters {
+    pub fn new() -> HouseholdParameters {
+        HouseholdParameters { size_distribution: Vec::new(), age_composition: Vec::new() }
+    }
+}
+
+impl Default for HouseholdParameters {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NetworkStructure {
+
+    pub fn new_households(n: usize, partitions: Vec<usize>, household_params: &HouseholdParameters) -> NetworkStructure {
+        // network of household contacts only, for use as a layer of its own
+        let mut last_idx = 0;
+        let ages: Vec<usize> = partitions
+            .iter()
+            .enumerate()
+            .flat_map(|(i,x)| {
+                let answer = vec![i; *x - last_idx];
+                last_idx = *x;
+                answer
+            })
+            .collect();
+        let mut network_structure = NetworkStructure {
+            adjacency_matrix: CooMatrix::new(n,n),
+            degree: vec![0.0; n],
+            age_brackets: ages,
+            household: Vec::new(),
+            layers: Vec::new(),
+            edge_layer: Vec::new()
+        };
+        network_structure.add_households(household_params);
+        network_structure
+    }
+
+    pub fn with_households(mut self, file_path: &str) -> NetworkStructure {
+        // two level network, the household layer on top of any community generator
+        let household_params = read_household_json(file_path);
+        self.add_households(&household_params);
+        self
+    }
+
+    pub fn add_households(&mut self, household_params: &HouseholdParameters) {
+        let mut rng: ThreadRng = rand::thread_rng();
+        let n = self.degree.len();
+        self.household = assign_households(&self.age_brackets, n, household_params, &mut rng);
+
+        // members of a household are all connected in the home layer, skipping contacts the
+        // community layer already has
+        let mut existing: HashSet<(usize, usize)> = self.adjacency_matrix
+            .triplet_iter()
+            .map(|(i, j, _)| (i, j))
+            .collect();
+        let mut members: Vec<Vec<usize>> = vec![Vec::new(); self.household.iter().max().map_or(0, |x| x + 1)];
+        for (node, id) in self.household.iter().enumerate() {
+            members[*id].push(node);
+        }
+        let layer = self.layer_id("home");
+        for house in members.iter() {
+            for (idx, &i) in house.iter().enumerate() {
+                for &j in house.iter().take(idx) {
+                    if existing.insert((i, j)) {
+                        existing.insert((j, i));
+                        self.push_edge(i, j, 1.0, layer);
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn assign_households<R: Rng>(age_brackets: &[usize], n: usize, household_params: &HouseholdParameters, rng: &mut R) -> Vec<usize> {
+    // networks without age brackets are treated as a single bracket
+    let brackets: Vec<usize> = if age_brackets.len() == n { age_brackets.to_vec() } else { vec![0; n] };
+    let num_brackets = brackets.iter().max().map_or(0, |x| x + 1);
+    // unassigned nodes of each age bracket in random order
+    let mut pools: Vec<Vec<usize>> = vec![Vec::new(); num_brackets];
+    for (node, bracket) in brackets.iter().enumerate() {
+        pools[*bracket].push(node);
+    }
+    pools.iter_mut().for_each(|pool| pool.shuffle(rng));
+
+    let size_dist = match WeightedIndex::new(&household_params.size_distribution) {
+        Ok(dist) => dist,
+        Err(err) => {
+            eprintln!("Error: {}, placing everyone in their own household", err);
+            return (0..n).collect()
+        }
+    };
+    let mut household: Vec<usize> = vec![0; n];
+    let mut remaining = n;
+    let mut id: usize = 0;
+    while remaining > 0 {
+        let size = size_dist.sample(rng) + 1;
+        // sizes beyond the listed compositions use the largest one
+        let composition: &[f64] = household_params.age_composition
+            .get(size - 1)
+            .or(household_params.age_composition.last())
+            .map_or(&[], |x| x.as_slice());
+        for _ in 0..size {
+            // draw each member's bracket among those with people left to place
+            let weights: Vec<f64> = (0..num_brackets)
+                .map(|b| if pools[b].is_empty() { 0.0 } else { composition.get(b).copied().unwrap_or(0.0) })
+                .collect();
+            let bracket = match WeightedIndex::new(&weights) {
+                Ok(dist) => dist.sample(rng),
+                // the composition has no mass left, fill from any bracket with people left
+                Err(_) => match pools.iter().position(|pool| !pool.is_empty()) {
+                    Some(b) => b,
+                    None => break
+                }
+            };
+            let node = pools[bracket].pop().unwrap();
+            household[node] = id;
+            remaining -= 1;
+        }
+        id += 1;
+    }
+    household
+}
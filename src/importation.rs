@@ -0,0 +1,122 @@
+use crate::periods::PeriodDistribution;
+use crate::random_graphs::*;
+use crate::seeding::SeedState;
+use crate::write_to_file::read_importation_csv;
+use rand::prelude::*;
+use serde::Serialize;
+
+// Infections from outside the network, e.g. brought back by travellers. Each day a Poisson number
+// of randomly chosen nodes are exposed, which only takes if the node is susceptible, and then with
+// the chance given by its susceptibility and vaccine protection. Runs carry on while imports can
+// still happen, so waning immunity can lead to endemic dynamics.
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum InfectionSource {
+    Node(usize),
+    Imported
+}
+
+#[derive(Clone, Debug)]
+pub enum ImportRate {
+    // mean imports per day
+    Constant(f64),
+    // mean imports on each day, after the end either starting again or keeping the last value
+    Series { values: Vec<f64>, repeat: bool }
+}
+
+#[derive(Clone, Debug)]
+pub struct Importation {
+    pub rate: ImportRate,
+    // relative chance of an import landing in each age bracket, None for every node alike
+    pub age_distribution: Option<Vec<f64>>,
+    pub state: SeedState
+}
+
+impl Importation {
+
+    pub fn new(rate: ImportRate) -> Importation {
+        Importation { rate, age_distribution: None, state: SeedState::Exposed }
+    }
+
+    pub fn from_file(file_path: &str, repeat: bool) -> Importation {
+        // day,imports rows, each rate holding until the next row
+        Importation::new(ImportRate::Series { values: read_importation_csv(file_path), repeat })
+    }
+
+    pub fn mean(&self, day: usize) -> f64 {
+        match &self.rate {
+            ImportRate::Constant(x) => *x,
+            ImportRate::Series { values, repeat } => {
+                match (values.is_empty(), repeat) {
+                    (true, _) => 0.0,
+                    (false, true) => values[day % values.len()],
+                    (false, false) => values[day.min(values.len() - 1)]
+                }
+            }
+        }
+    }
+
+    pub fn importing_from(&self, day: usize) -> bool {
+        // whether there can be any imports on this day or later
+        match &self.rate {
+            ImportRate::Constant(x) => *x > 0.0,
+            ImportRate::Series { values, repeat: true } => values.iter().any(|x| *x > 0.0),
+            ImportRate::Series { values, repeat: false } => {
+                values.iter().skip(day.min(values.len().saturating_sub(1))).any(|x| *x > 0.0)
+            }
+        }
+    }
+
+    pub(crate) fn draw<R: Rng>(&self, network_structure: &NetworkStructure, network_properties: &NetworkProperties, day: usize, rng: &mut R) -> Vec<usize> {
+        // nodes the day's imports land on, each kept with the chance that the node can be infected
+        // given its susceptibility and vaccine protection, capped at one. Kept nodes may still
+        // be immune or already infected
+        let count = PeriodDistribution::Poisson { mean: self.mean(day) }.sample(rng);
+        let n = network_properties.nodal_states.len();
+        if count == 0 || n == 0 {
+            return Vec::new()
+        }
+        let landed: Vec<usize> = match &self.age_distribution {
+            None => (0..count).map(|_| rng.gen_range(0..n)).collect(),
+            Some(weights) => {
+                let mut members: Vec<Vec<usize>> = vec![Vec::new(); weights.len()];
+                for i in 0..n {
+                    if let Some(x) = members.get_mut(network_structure.age_brackets.get(i).copied().unwrap_or(0)) {
+                        x.push(i);
+                    }
+                }
+                // brackets without any nodes are left out
+                let weights: Vec<f64> = weights.iter().zip(members.iter()).map(|(w, x)| if x.is_empty() { 0.0 } else { *w }).collect();
+                let total: f64 = weights.iter().sum();
+                if total <= 0.0 {
+                    return Vec::new()
+                }
+                (0..count)
+                    .map(|_| {
+                        let mut u = rng.gen::<f64>() * total;
+                        let bracket = weights.iter()
+                            .position(|w| { u -= w; u < 0.0 })
+                            .unwrap_or_else(|| weights.iter().rposition(|w| *w > 0.0).unwrap());
+                        *members[bracket].choose(rng).unwrap()
+                    })
+                    .collect()
+            }
+        };
+        landed
+            .into_iter()
+            .filter(|&j| {
+                let protection = network_properties.vaccine_status.get(j).map_or(0.0, |x| x.infection_protection);
+                rng.gen::<f64>() < (network_properties.susceptibility(network_structure, j) * (1.0 - protection)).min(1.0)
+            })
+            .collect()
+    }
+}
+
+impl NetworkProperties {
+    pub fn outbreak_over(&self) -> bool {
+        // nobody exposed or infectious, and no more infections to come from outside
+        let day = self.results.len().saturating_sub(1);
+        let last = self.results.last().unwrap();
+        last[1] + last[2] == 0 && !self.importation.as_ref().is_some_and(|x| x.importing_from(day))
+    }
+}
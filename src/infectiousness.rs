@@ -0,0 +1,49 @@
+use crate::importation::InfectionSource;
+use crate::periods::PeriodDistribution;
+use crate::random_graphs::*;
+use serde::{Deserialize, Serialize};
+
+// Infectiousness that changes over the course of infection. The profile multiplies the chance
+// of transmission on each day since a node became infectious, so the generation interval is the
+// latent period plus a draw from the profile cut short by the end of the infectious period.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "family", rename_all = "snake_case")]
+pub enum InfectiousnessProfile {
+    // relative infectiousness on each day from the first infectious day, zero after the last
+    Vector { values: Vec<f64> },
+    // the shape of a period distribution, e.g. gamma, scaled to sum to total over all days.
+    // A total equal to the mean infectious period keeps the overall chance of transmission
+    Parametric { distribution: PeriodDistribution, total: f64 }
+}
+
+impl InfectiousnessProfile {
+    pub fn values(&self) -> Vec<f64> {
+        match self {
+            InfectiousnessProfile::Vector { values } => values.clone(),
+            InfectiousnessProfile::Parametric { distribution, total } => {
+                distribution.pmf_vec().iter().map(|x| x * total).collect()
+            }
+        }
+    }
+}
+
+impl NetworkProperties {
+    pub fn days_infectious(&self, node: usize, day: usize) -> Option<usize> {
+        // days since the node became infectious, counting its first infectious day as zero
+        self.infectious_day.get(node).copied().flatten().map(|x| day.saturating_sub(x))
+    }
+}
+
+pub fn generation_intervals(output: &Output) -> Vec<usize> {
+    // days from the infection of each infector to each of their transmissions, with initial
+    // cases infected on day 0. Transmissions are logged in order, so reinfections are handled
+    let mut infected: Vec<usize> = vec![0; output.infected_by.len()];
+    let mut intervals: Vec<usize> = Vec::new();
+    for &(day, source, infectee) in output.transmissions.iter() {
+        if let InfectionSource::Node(infector) = source {
+            intervals.push(day - infected[infector]);
+        }
+        infected[infectee] = day;
+    }
+    intervals
+}
@@ -0,0 +1,316 @@
+use crate::random_graphs::*;
+use crate::run_model::{run_days, step_model};
+use crate::seeding::SeedingError;
+use crate::timeline::NetworkTimeline;
+use rand::prelude::*;
+use std::collections::HashMap;
+
+// Interventions are called before and after every day of run_model_with_interventions, and can
+// change the network, the parameters or the nodal states. The built in ones act through the
+// layer, node and edge multipliers of NetworkProperties, so they can be lifted again.
+
+pub trait Intervention {
+    fn before_step(&mut self, _network_structure: &mut NetworkStructure, _network_properties: &mut NetworkProperties, _day: usize) {}
+    fn after_step(&mut self, _network_structure: &mut NetworkStructure, _network_properties: &mut NetworkProperties, _day: usize) {}
+    // add anything worth keeping to the output at the end of the run
+    fn record(&self, _output: &mut Output) {}
+    // whether before_step replaced the network on this day
+    fn changed_network(&self, _day: usize) -> bool { false }
+    // the network was replaced and the edge multipliers cleared, so anything set on entries of
+    // the old adjacency matrix has to be set again
+    fn network_changed(&mut self, _network_structure: &NetworkStructure, _network_properties: &mut NetworkProperties) {}
+}
+
+#[derive(Clone, Debug)]
+pub enum Trigger {
+    Day(usize),
+    // fraction of the population currently infectious
+    PrevalenceAbove(f64),
+    PrevalenceBelow(f64),
+    // fraction of the population newly infected on the previous day
+    IncidenceAbove(f64),
+    IncidenceBelow(f64)
+}
+
+impl Trigger {
+    pub fn fired(&self, network_properties: &NetworkProperties, day: usize) -> bool {
+        let n = network_properties.nodal_states.len().max(1) as f64;
+        let results = &network_properties.results;
+        let prevalence = results.last().map_or(0.0, |x| x[2] as f64 / n);
+        // drop in susceptibles, which misses reinfections of nodes whose immunity waned that day
+        let incidence = match results.len() {
+            0 | 1 => 0.0,
+            len => results[len-2][0].saturating_sub(results[len-1][0]) as f64 / n
+        };
+        match self {
+            Trigger::Day(x) => day == *x,
+            Trigger::PrevalenceAbove(x) => prevalence >= *x,
+            Trigger::PrevalenceBelow(x) => prevalence < *x,
+            Trigger::IncidenceAbove(x) => incidence >= *x,
+            Trigger::IncidenceBelow(x) => incidence < *x
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Schedule {
+    pub start: Trigger,
+    // the intervention stays in place if there is no end, and can start again after ending
+    pub end: Option<Trigger>,
+    pub active: bool,
+    // days the intervention started and ended
+    pub log: Vec<(usize, bool)>
+}
+
+impl Schedule {
+    pub fn new(start: Trigger, end: Option<Trigger>) -> Schedule {
+        Schedule { start, end, active: false, log: Vec::new() }
+    }
+
+    pub fn between(start: usize, end: usize) -> Schedule {
+        Schedule::new(Trigger::Day(start), Some(Trigger::Day(end)))
+    }
+
+    pub fn update(&mut self, network_properties: &NetworkProperties, day: usize) -> Option<bool> {
+        // Some(true) on the day the intervention starts, Some(false) on the day it ends
+        let change = if !self.active && self.start.fired(network_properties, day) {
+            Some(true)
+        } else if self.active && self.end.as_ref().is_some_and(|x| x.fired(network_properties, day)) {
+            Some(false)
+        } else {
+            None
+        };
+        if let Some(active) = change {
+            self.active = active;
+            self.log.push((day, active));
+        }
+        change
+    }
+
+    fn record(&self, name: &str, output: &mut Output) {
+        for (day, active) in self.log.iter() {
+            let status = if *active { "started" } else { "ended" };
+            output.events.push((*day, format!("{name} {status}")));
+        }
+    }
+}
+
+pub struct SchoolClosure {
+    // contacts of the school layer, or if there is no such layer those between school ages
+    pub layer: Option<String>,
+    pub age_brackets: Vec<usize>,
+    pub schedule: Schedule,
+    saved: Vec<(usize, f64)>
+}
+
+impl SchoolClosure {
+    pub fn new(layer: Option<&str>, age_brackets: Vec<usize>, schedule: Schedule) -> SchoolClosure {
+        SchoolClosure { layer: layer.map(String::from), age_brackets, schedule, saved: Vec::new() }
+    }
+
+    fn close(&mut self, network_structure: &NetworkStructure, network_properties: &mut NetworkProperties) {
+        self.saved.clear();
+        // a named layer that is missing closes nothing, rather than falling back to the age brackets
+        let layer = match &self.layer {
+            Some(name) => match network_structure.layers.iter().position(|x| x == name) {
+                Some(layer) => Some(layer),
+                None => {
+                    println!("There is no layer called {name}");
+                    return
+                }
+            },
+            None => None
+        };
+        let school_age = |node: usize| network_structure.age_brackets.get(node).is_some_and(|x| self.age_brackets.contains(x));
+        let edges: Vec<usize> = network_structure.adjacency_matrix
+            .triplet_iter()
+            .enumerate()
+            .filter(|(edge, (i, j, _))| match layer {
+                Some(layer) => network_structure.edge_layer(*edge) == layer,
+                None => school_age(*i) && school_age(*j)
+            })
+            .map(|(edge, _)| edge)
+            .collect();
+        self.saved = set_edge_multipliers(network_structure, network_properties, &edges, 0.0);
+    }
+}
+
+impl Intervention for SchoolClosure {
+    fn before_step(&mut self, network_structure: &mut NetworkStructure, network_properties: &mut NetworkProperties, day: usize) {
+        match self.schedule.update(network_properties, day) {
+            Some(true) => self.close(network_structure, network_properties),
+            Some(false) => restore_edge_multipliers(network_properties, &self.saved),
+            None => ()
+        }
+    }
+
+    fn record(&self, output: &mut Output) {
+        self.schedule.record("school closure", output);
+    }
+
+    fn network_changed(&mut self, network_structure: &NetworkStructure, network_properties: &mut NetworkProperties) {
+        if self.schedule.active {
+            self.close(network_structure, network_properties);
+        }
+    }
+}
+
+pub struct Shielding {
+    // everyone in these age brackets has their contacts reduced by the given fraction
+    pub age_brackets: Vec<usize>,
+    pub reduction: f64,
+    pub schedule: Schedule,
+    saved: Vec<(usize, f64)>
+}
+
+impl Shielding {
+    pub fn new(age_brackets: Vec<usize>, reduction: f64, schedule: Schedule) -> Shielding {
+        Shielding { age_brackets, reduction, schedule, saved: Vec::new() }
+    }
+}
+
+impl Intervention for Shielding {
+    fn before_step(&mut self, network_structure: &mut NetworkStructure, network_properties: &mut NetworkProperties, day: usize) {
+        match self.schedule.update(network_properties, day) {
+            Some(true) => {
+                let n = network_structure.degree.len();
+                if network_properties.node_multipliers.len() < n {
+                    network_properties.node_multipliers.resize(n, 1.0);
+                }
+                self.saved = network_structure.age_brackets
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, age)| self.age_brackets.contains(age))
+                    .map(|(node, _)| (node, network_properties.node_multipliers[node]))
+                    .collect();
+                for (node, multiplier) in self.saved.iter() {
+                    network_properties.node_multipliers[*node] = multiplier * (1.0 - self.reduction);
+                }
+            },
+            Some(false) => {
+                for (node, multiplier) in self.saved.iter() {
+                    network_properties.node_multipliers[*node] = *multiplier;
+                }
+            },
+            None => ()
+        }
+    }
+
+    fn record(&self, output: &mut Output) {
+        self.schedule.record("shielding", output);
+    }
+}
+
+pub struct ContactReduction {
+    // each contact, of one layer or all of them, is dropped with this probability
+    pub fraction: f64,
+    pub layer: Option<String>,
+    pub schedule: Schedule,
+    saved: Vec<(usize, f64)>
+}
+
+impl ContactReduction {
+    pub fn new(fraction: f64, layer: Option<&str>, schedule: Schedule) -> ContactReduction {
+        ContactReduction { fraction, layer: layer.map(String::from), schedule, saved: Vec::new() }
+    }
+
+    fn reduce(&mut self, network_structure: &NetworkStructure, network_properties: &mut NetworkProperties) {
+        let mut rng: ThreadRng = rand::thread_rng();
+        let layer = self.layer.as_ref().and_then(|name| network_structure.layers.iter().position(|x| x == name));
+        // both directions of a contact are dropped together
+        let mut dropped: HashMap<(usize, usize), bool> = HashMap::new();
+        let edges: Vec<usize> = network_structure.adjacency_matrix
+            .triplet_iter()
+            .enumerate()
+            .filter(|(edge, _)| layer.is_none_or(|layer| network_structure.edge_layer(*edge) == layer))
+            .filter(|(_, (i, j, _))| {
+                *dropped.entry((*i.max(j), *i.min(j))).or_insert_with(|| rng.gen::<f64>() < self.fraction)
+            })
+            .map(|(edge, _)| edge)
+            .collect();
+        self.saved = set_edge_multipliers(network_structure, network_properties, &edges, 0.0);
+    }
+}
+
+impl Intervention for ContactReduction {
+    fn before_step(&mut self, network_structure: &mut NetworkStructure, network_properties: &mut NetworkProperties, day: usize) {
+        match self.schedule.update(network_properties, day) {
+            Some(true) => self.reduce(network_structure, network_properties),
+            Some(false) => restore_edge_multipliers(network_properties, &self.saved),
+            None => ()
+        }
+    }
+
+    fn record(&self, output: &mut Output) {
+        self.schedule.record("contact reduction", output);
+    }
+
+    fn network_changed(&mut self, network_structure: &NetworkStructure, network_properties: &mut NetworkProperties) {
+        // the contacts of the new network are dropped afresh
+        if self.schedule.active {
+            self.reduce(network_structure, network_properties);
+        }
+    }
+}
+
+impl Intervention for NetworkTimeline {
+    fn before_step(&mut self, network_structure: &mut NetworkStructure, _network_properties: &mut NetworkProperties, day: usize) {
+        if self.update(day) {
+            *network_structure = self.network.clone();
+        }
+    }
+
+    fn changed_network(&self, day: usize) -> bool {
+        self.changed_on.last() == Some(&day)
+    }
+}
+
+fn set_edge_multipliers(network_structure: &NetworkStructure, network_properties: &mut NetworkProperties, edges: &[usize], multiplier: f64) -> Vec<(usize, f64)> {
+    // returns the previous values, so they can be put back
+    let nnz = network_structure.adjacency_matrix.nnz();
+    if network_properties.edge_multipliers.len() < nnz {
+        network_properties.edge_multipliers.resize(nnz, 1.0);
+    }
+    edges
+        .iter()
+        .map(|&edge| {
+            let previous = network_properties.edge_multipliers[edge];
+            network_properties.edge_multipliers[edge] = previous * multiplier;
+            (edge, previous)
+        })
+        .collect()
+}
+
+fn restore_edge_multipliers(network_properties: &mut NetworkProperties, saved: &[(usize, f64)]) {
+    for (edge, multiplier) in saved.iter() {
+        if let Some(x) = network_properties.edge_multipliers.get_mut(*edge) {
+            *x = *multiplier;
+        }
+    }
+}
+
+pub fn run_model_with_interventions(network_structure: &NetworkStructure, network_properties: &mut NetworkProperties, maxtime: f64, initially_infected: f64, interventions: &mut [Box<dyn Intervention>]) -> Result<Output, SeedingError> {
+    // as run_model, on a copy of the network that the interventions are free to change
+    let mut network_structure = network_structure.clone();
+    let mut output = run_days(network_properties, maxtime, initially_infected, |network_properties, day, rng| {
+        for intervention in interventions.iter_mut() {
+            intervention.before_step(&mut network_structure, network_properties, day);
+        }
+        // edge multipliers belong to entries of the old network
+        if interventions.iter().any(|x| x.changed_network(day)) {
+            network_properties.edge_multipliers.clear();
+            for intervention in interventions.iter_mut() {
+                intervention.network_changed(&network_structure, network_properties);
+            }
+        }
+        step_model(&network_structure, network_properties, rng);
+        for intervention in interventions.iter_mut() {
+            intervention.after_step(&mut network_structure, network_properties, day);
+        }
+    })?;
+    for intervention in interventions.iter() {
+        intervention.record(&mut output);
+    }
+    output.events.sort_by_key(|(day, _)| *day);
+    Ok(output)
+}
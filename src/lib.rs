@@ -5,3 +5,26 @@ pub mod run_scenarios;
 pub mod useful_functions;
 pub mod run_model;
 pub mod multinomial_sample;
+pub mod ode_solver;
+pub mod edge_based_model;
+pub mod mean_field;
+pub mod degree_distributions;
+pub mod fitting;
+pub mod households;
+pub mod multilayer;
+pub mod edge_weights;
+pub mod dynamic_network;
+pub mod temporal_network;
+pub mod timeline;
+pub mod interventions;
+pub mod vaccination;
+pub mod targeting;
+pub mod contact_tracing;
+pub mod natural_history;
+pub mod severity;
+pub mod periods;
+pub mod infectiousness;
+pub mod heterogeneity;
+pub mod seasonality;
+pub mod seeding;
+pub mod importation;
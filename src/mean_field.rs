@@ -0,0 +1,140 @@
+use crate::random_graphs::{NetworkProperties, Output};
+use crate::useful_functions::*;
+use crate::ode_solver::*;
+
+// Well-mixed, age-structured SEIR(S) model. Contacts between age blocks are taken from
+// the same rates matrix and partitions used by new_sbm, so each network scenario has a
+// mean-field baseline that only differs by the absence of network structure.
+
+pub struct MeanFieldModel {
+    pub group_sizes: Vec<usize>,
+    // contact_mat[a][b] is the mean number of contacts a person in block a has with block b
+    pub contact_mat: Vec<Vec<f64>>,
+    pub beta: f64,
+    pub sigma: f64,
+    pub gamma: f64,
+    // rate of waning immunity, zero without the fourth parameter
+    pub omega: f64,
+    pub initially_infected: f64,
+    // relative susceptibility and infectiousness of each block
+    pub susceptibility: Vec<f64>,
+    pub infectiousness: Vec<f64>
+}
+
+impl MeanFieldModel {
+
+    pub fn new(partitions: &[usize], rates_mat: &[Vec<f64>], parameters: &[f64], initially_infected: f64) -> MeanFieldModel {
+        // periods follow step_model, which keeps a node in each state for the sampled days plus one
+        MeanFieldModel {
+            group_sizes: group_sizes(partitions),
+            contact_mat: sbm_contact_matrix(rates_mat, partitions),
+            beta: -(1.0 - parameters[0]).ln(),
+            sigma: 1.0 / (parameters[1] + 1.0),
+            gamma: 1.0 / (parameters[2] + 1.0),
+            omega: parameters.get(3).map_or(0.0, |x| 1.0 / (x + 1.0)),
+            initially_infected,
+            susceptibility: vec![1.0; partitions.len()],
+            infectiousness: vec![1.0; partitions.len()]
+        }
+    }
+
+    pub fn with_multipliers(mut self, susceptibility: Vec<f64>, infectiousness: Vec<f64>) -> MeanFieldModel {
+        self.susceptibility = susceptibility;
+        self.infectiousness = infectiousness;
+        self
+    }
+
+    pub fn from_network_properties(partitions: &[usize], rates_mat: &[Vec<f64>], network_properties: &NetworkProperties, initially_infected: f64) -> MeanFieldModel {
+        let (susceptibility, infectiousness) = network_properties.block_multipliers(partitions);
+        MeanFieldModel::new(partitions, rates_mat, &network_properties.parameters, initially_infected)
+            .with_multipliers(susceptibility, infectiousness)
+    }
+
+    pub fn initial_conditions(&self) -> Vec<f64> {
+        // S, E, I, R counts for each block in turn
+        self.group_sizes
+            .iter()
+            .flat_map(|&size| {
+                let infected = (size as f64) * self.initially_infected;
+                vec![size as f64 - infected, 0.0, infected, 0.0]
+            })
+            .collect()
+    }
+
+    pub fn derivatives(&self, y: &[f64]) -> Vec<f64> {
+        let blocks = self.group_sizes.len();
+        // infectious pressure from each block, weighted by how infectious its members are
+        let prevalence: Vec<f64> = (0..blocks)
+            .map(|b| self.infectiousness[b] * y[4*b + 2] / (self.group_sizes[b] as f64))
+            .collect();
+        let mut dy: Vec<f64> = vec![0.0; y.len()];
+        for a in 0..blocks {
+            let force: f64 = self.beta * self.susceptibility[a] * self.contact_mat[a]
+                .iter()
+                .zip(prevalence.iter())
+                .map(|(c, i)| c * i)
+                .sum::<f64>();
+            let (s, e, i, r) = (y[4*a], y[4*a + 1], y[4*a + 2], y[4*a + 3]);
+            dy[4*a] = -force * s + self.omega * r;
+            dy[4*a + 1] = force * s - self.sigma * e;
+            dy[4*a + 2] = self.sigma * e - self.gamma * i;
+            dy[4*a + 3] = self.gamma * i - self.omega * r;
+        }
+        dy
+    }
+
+    pub fn next_generation_matrix(&self) -> Vec<Vec<f64>> {
+        // expected infections in block a caused by one infected person in block b
+        let blocks = self.group_sizes.len();
+        (0..blocks)
+            .map(|a| {
+                (0..blocks).map(|b| {
+                    self.beta * self.susceptibility[a] * self.infectiousness[b] * self.contact_mat[a][b]
+                        * (self.group_sizes[a] as f64) / (self.group_sizes[b] as f64) / self.gamma
+                })
+                .collect()
+            })
+            .collect()
+    }
+
+    pub fn r0(&self) -> f64 {
+        spectral_radius(&self.next_generation_matrix())
+    }
+}
+
+pub fn run_mean_field_model(model: &MeanFieldModel, maxtime: f64, solver: OdeSolver) -> Output {
+    let trajectory = solve_daily(|_, y| model.derivatives(y), model.initial_conditions(), maxtime, solver);
+    let mut output: Output = Output::new();
+    // sum blocks into the compartment order used by count_states
+    output.seir = trajectory
+        .iter()
+        .map(|y| {
+            (0..4).map(|state| {
+                y.iter()
+                    .skip(state)
+                    .step_by(4)
+                    .sum::<f64>()
+                    .round()
+                    .max(0.0) as usize
+            })
+            .collect()
+        })
+        .collect();
+    output
+}
+
+pub fn sbm_contact_matrix(rates_mat: &[Vec<f64>], partitions: &[usize]) -> Vec<Vec<f64>> {
+    // new_sbm links a node in block i to one in block j <= i with probability
+    // rates_mat[i][j] / size_i, so only the lower triangle sets the number of edges
+    let sizes = group_sizes(partitions);
+    let blocks = sizes.len();
+    let mut contact_mat: Vec<Vec<f64>> = vec![vec![0.0; blocks]; blocks];
+    for i in 0..blocks {
+        for j in 0..=i {
+            let edges = (sizes[j] as f64) * rates_mat[i][j];
+            contact_mat[i][j] = edges / (sizes[i] as f64);
+            contact_mat[j][i] = edges / (sizes[j] as f64);
+        }
+    }
+    contact_mat
+}
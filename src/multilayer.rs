@@ -0,0 +1,134 @@
+use crate::random_graphs::{NetworkProperties, NetworkStructure};
+use nalgebra_sparse::coo::CooMatrix;
+
+// Contact settings (home, work, school, other) are layers of a single NetworkStructure.
+// Every entry of the adjacency matrix has a layer id in edge_layer, and its value is the
+// weight of the contact. Networks built without layers are a single layer with id 0.
+
+impl NetworkStructure {
+
+    pub fn new_multilayer(layers: Vec<(&str, NetworkStructure)>) -> NetworkStructure {
+        // each layer may come from a different generator or parameter file, on the same nodes
+        let n = layers.first().map_or(0, |(_, network)| network.degree.len());
+        let age_brackets = layers
+            .iter()
+            .map(|(_, network)| network.age_brackets.clone())
+            .find(|ages| !ages.is_empty())
+            .unwrap_or_default();
+        let mut network_structure = NetworkStructure {
+            adjacency_matrix: CooMatrix::new(n,n),
+            degree: vec![0.0; n],
+            age_brackets,
+            household: Vec::new(),
+            layers: Vec::new(),
+            edge_layer: Vec::new()
+        };
+        for (name, layer) in layers.iter() {
+            network_structure.add_layer(name, layer);
+        }
+        network_structure
+    }
+
+    pub fn add_layer(&mut self, name: &str, other: &NetworkStructure) {
+        // append the edges of another network on the same nodes as the layer called name
+        assert_eq!(self.degree.len(), other.degree.len());
+        let layer = self.layer_id(name);
+        for (i, j, weight) in other.adjacency_matrix.triplet_iter() {
+            self.adjacency_matrix.push(i, j, *weight);
+            self.edge_layer.push(layer);
+            self.degree[i] += 1.0;
+        }
+        if self.household.is_empty() {
+            self.household = other.household.clone();
+        }
+    }
+
+    pub fn layer_id(&mut self, name: &str) -> usize {
+        // find the layer by name, creating it if it does not exist yet
+        if self.layers.is_empty() {
+            // the edges already present become the community layer
+            self.layers.push(String::from("community"));
+            self.edge_layer = vec![0; self.adjacency_matrix.nnz()];
+            if self.adjacency_matrix.nnz() == 0 {
+                self.layers[0] = String::from(name);
+                return 0
+            }
+        }
+        match self.layers.iter().position(|x| x == name) {
+            Some(layer) => layer,
+            None => {
+                self.layers.push(String::from(name));
+                self.layers.len() - 1
+            }
+        }
+    }
+
+    pub fn edge_layer(&self, edge: usize) -> usize {
+        self.edge_layer.get(edge).copied().unwrap_or(0)
+    }
+
+    pub fn push_edge(&mut self, i: usize, j: usize, weight: f64, layer: usize) {
+        // undirected contact, stored in both directions
+        self.adjacency_matrix.push(i, j, weight);
+        self.adjacency_matrix.push(j, i, weight);
+        if !self.layers.is_empty() {
+            self.edge_layer.push(layer);
+            self.edge_layer.push(layer);
+        }
+        self.degree[i] += 1.0;
+        self.degree[j] += 1.0;
+    }
+
+    pub fn truncate_edges(&mut self, len: usize) {
+        // drop the entries of the adjacency matrix from len onwards, e.g. the edges pushed after
+        // a backbone, leaving the indices of the earlier entries as they were
+        let (rows, cols) = (self.adjacency_matrix.nrows(), self.adjacency_matrix.ncols());
+        let (mut row_idx, mut col_idx, mut values) = std::mem::replace(&mut self.adjacency_matrix, CooMatrix::new(rows, cols)).disassemble();
+        for &i in row_idx.iter().skip(len) {
+            self.degree[i] -= 1.0;
+        }
+        row_idx.truncate(len);
+        col_idx.truncate(len);
+        values.truncate(len);
+        self.edge_layer.truncate(len);
+        self.adjacency_matrix = CooMatrix::try_from_triplets(rows, cols, row_idx, col_idx, values).unwrap();
+    }
+
+    pub fn neighbours(&self) -> Vec<Vec<(usize, usize)>> {
+        // neighbour and adjacency matrix entry of every edge, indexed by node
+        let mut neighbours: Vec<Vec<(usize, usize)>> = vec![Vec::new(); self.degree.len()];
+        for (edge, (i, j, _)) in self.adjacency_matrix.triplet_iter().enumerate() {
+            neighbours[i].push((j, edge));
+        }
+        neighbours
+    }
+
+    pub fn layer_degree(&self, layer: usize) -> Vec<f64> {
+        let mut degree: Vec<f64> = vec![0.0; self.degree.len()];
+        for (edge, (i, _, _)) in self.adjacency_matrix.triplet_iter().enumerate() {
+            if self.edge_layer(edge) == layer {
+                degree[i] += 1.0;
+            }
+        }
+        degree
+    }
+}
+
+impl NetworkProperties {
+
+    pub fn set_layer_multiplier(&mut self, network_structure: &NetworkStructure, name: &str, multiplier: f64) {
+        match network_structure.layers.iter().position(|x| x == name) {
+            Some(layer) => {
+                if self.layer_multipliers.len() <= layer {
+                    self.layer_multipliers.resize(layer + 1, 1.0);
+                }
+                self.layer_multipliers[layer] = multiplier;
+            },
+            None => println!("There is no layer called {name}")
+        }
+    }
+
+    pub fn layer_multiplier(&self, layer: usize) -> f64 {
+        self.layer_multipliers.get(layer).copied().unwrap_or(1.0)
+    }
+}
@@ -0,0 +1,44 @@
+use crate::random_graphs::{NetworkProperties, State};
+
+// Extended course of infection, used when NetworkProperties::natural_history is set. Exposed nodes
+// become presymptomatic, then symptomatic or asymptomatic for the infectious period of parameters[2].
+
+#[derive(Clone, Debug)]
+pub struct NaturalHistory {
+    // mean days from becoming infectious to symptom onset
+    pub presymptomatic_period: f64,
+    // chance of never developing symptoms, for each age bracket
+    pub asymptomatic_probability: Vec<f64>,
+    // infectiousness of each stage relative to parameters[0]
+    pub presymptomatic_infectiousness: f64,
+    pub symptomatic_infectiousness: f64,
+    pub asymptomatic_infectiousness: f64
+}
+
+impl NaturalHistory {
+    pub fn asymptomatic_probability(&self, age_bracket: usize) -> f64 {
+        self.asymptomatic_probability.get(age_bracket).copied().unwrap_or(0.0)
+    }
+}
+
+impl State {
+    pub fn infectious(&self) -> bool {
+        matches!(self, State::Infected(_) | State::Presymptomatic(_) | State::Symptomatic(_) | State::Asymptomatic(_))
+    }
+
+    pub fn symptomatic(&self) -> bool {
+        // without the extended natural history every infectious node is taken to have symptoms
+        matches!(self, State::Infected(_) | State::Symptomatic(_))
+    }
+}
+
+impl NetworkProperties {
+    pub fn relative_infectiousness(&self, node: usize) -> f64 {
+        match (&self.natural_history, &self.nodal_states[node]) {
+            (Some(history), State::Presymptomatic(_)) => history.presymptomatic_infectiousness,
+            (Some(history), State::Symptomatic(_)) => history.symptomatic_infectiousness,
+            (Some(history), State::Asymptomatic(_)) => history.asymptomatic_infectiousness,
+            _ => 1.0
+        }
+    }
+}
@@ -0,0 +1,124 @@
+// Runge-Kutta integrators for the deterministic comparators of the network model.
+// Both solvers report the state once per simulated day, so trajectories line up
+// row for row with the daily results produced by run_model.
+
+#[derive(Clone, Copy, Debug)]
+pub enum OdeSolver {
+    // classic fourth order method with a fixed step size (in days)
+    RK4(f64),
+    // adaptive Dormand-Prince 5(4) method with a local error tolerance
+    RK45(f64),
+}
+
+pub fn solve_daily<F>(f: F, y0: Vec<f64>, maxtime: f64, solver: OdeSolver) -> Vec<Vec<f64>>
+where
+    F: Fn(f64, &[f64]) -> Vec<f64>,
+{
+    match solver {
+        OdeSolver::RK4(dt) => rk4(f, y0, maxtime, dt),
+        OdeSolver::RK45(tolerance) => rk45(f, y0, maxtime, tolerance),
+    }
+}
+
+pub fn rk4<F>(f: F, y0: Vec<f64>, maxtime: f64, dt: f64) -> Vec<Vec<f64>>
+where
+    F: Fn(f64, &[f64]) -> Vec<f64>,
+{
+    // round the step so that a whole number of steps fits in each day
+    let steps_per_day = (1.0 / dt).round().max(1.0) as usize;
+    let h = 1.0 / steps_per_day as f64;
+    let mut y = y0;
+    let mut t = 0.0;
+    let mut trajectory = vec![y.clone()];
+    for _ in 0..(maxtime as usize) {
+        for _ in 0..steps_per_day {
+            let k1 = f(t, &y);
+            let k2 = f(t + h / 2.0, &add_scaled(&y, &[(h / 2.0, &k1[..])]));
+            let k3 = f(t + h / 2.0, &add_scaled(&y, &[(h / 2.0, &k2[..])]));
+            let k4 = f(t + h, &add_scaled(&y, &[(h, &k3[..])]));
+            y = add_scaled(&y, &[(h / 6.0, &k1[..]), (h / 3.0, &k2[..]), (h / 3.0, &k3[..]), (h / 6.0, &k4[..])]);
+            t += h;
+        }
+        trajectory.push(y.clone());
+    }
+    trajectory
+}
+
+pub fn rk45<F>(f: F, y0: Vec<f64>, maxtime: f64, tolerance: f64) -> Vec<Vec<f64>>
+where
+    F: Fn(f64, &[f64]) -> Vec<f64>,
+{
+    // Dormand-Prince coefficients
+    const C: [f64; 7] = [0.0, 1.0 / 5.0, 3.0 / 10.0, 4.0 / 5.0, 8.0 / 9.0, 1.0, 1.0];
+    const A: [[f64; 6]; 7] = [
+        [0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+        [1.0 / 5.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+        [3.0 / 40.0, 9.0 / 40.0, 0.0, 0.0, 0.0, 0.0],
+        [44.0 / 45.0, -56.0 / 15.0, 32.0 / 9.0, 0.0, 0.0, 0.0],
+        [19372.0 / 6561.0, -25360.0 / 2187.0, 64448.0 / 6561.0, -212.0 / 729.0, 0.0, 0.0],
+        [9017.0 / 3168.0, -355.0 / 33.0, 46732.0 / 5247.0, 49.0 / 176.0, -5103.0 / 18656.0, 0.0],
+        [35.0 / 384.0, 0.0, 500.0 / 1113.0, 125.0 / 192.0, -2187.0 / 6784.0, 11.0 / 84.0],
+    ];
+    // fifth order weights are the last row of A, the embedded fourth order weights follow
+    const B4: [f64; 7] = [
+        5179.0 / 57600.0, 0.0, 7571.0 / 16695.0, 393.0 / 640.0,
+        -92097.0 / 339200.0, 187.0 / 2100.0, 1.0 / 40.0,
+    ];
+
+    let mut y = y0;
+    let mut t = 0.0;
+    let mut h: f64 = 0.1;
+    let mut trajectory = vec![y.clone()];
+    for day in 1..=(maxtime as usize) {
+        let t_end = day as f64;
+        while t < t_end - 1e-12 {
+            // never step past the next daily output
+            h = h.min(t_end - t);
+            let mut k: Vec<Vec<f64>> = Vec::with_capacity(7);
+            for stage in 0..7 {
+                let terms: Vec<(f64, &[f64])> = (0..stage)
+                    .map(|s| (h * A[stage][s], k[s].as_slice()))
+                    .collect();
+                let y_stage = add_scaled(&y, &terms);
+                k.push(f(t + C[stage] * h, &y_stage));
+            }
+            let fifth: Vec<(f64, &[f64])> = (0..6).map(|s| (h * A[6][s], k[s].as_slice())).collect();
+            let y_new = add_scaled(&y, &fifth);
+            // error estimate from the difference between the two embedded solutions
+            let error = (0..y.len())
+                .map(|i| {
+                    let diff: f64 = (0..7)
+                        .map(|s| {
+                            let b5 = if s < 6 { A[6][s] } else { 0.0 };
+                            h * (b5 - B4[s]) * k[s][i]
+                        })
+                        .sum();
+                    let scale = tolerance * (1.0 + y[i].abs().max(y_new[i].abs()));
+                    (diff / scale).powi(2)
+                })
+                .sum::<f64>()
+                .sqrt()
+                / (y.len().max(1) as f64).sqrt();
+            if error <= 1.0 {
+                t += h;
+                y = y_new;
+            }
+            // standard step size controller with a safety factor
+            let factor = if error == 0.0 { 5.0 } else { (0.9 * error.powf(-0.2)).clamp(0.2, 5.0) };
+            h *= factor;
+        }
+        trajectory.push(y.clone());
+    }
+    trajectory
+}
+
+fn add_scaled(y: &[f64], terms: &[(f64, &[f64])]) -> Vec<f64> {
+    // y + sum of scale * direction for each term
+    let mut result = y.to_vec();
+    for (scale, direction) in terms.iter() {
+        for (r, d) in result.iter_mut().zip(direction.iter()) {
+            *r += scale * d;
+        }
+    }
+    result
+}
@@ -0,0 +1,144 @@
+use crate::random_graphs::NetworkProperties;
+use crate::write_to_file::read_period_csv;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+use statrs::distribution::{ContinuousCDF, Discrete, Gamma, LogNormal, Poisson};
+
+// Lengths of the latent, infectious and immune periods. A node entering a stage is given a
+// number of days drawn from its distribution, and leaves the stage the day after it reaches zero.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "family", rename_all = "snake_case")]
+pub enum PeriodDistribution {
+    Fixed { days: usize },
+    Poisson { mean: f64 },
+    // rounded down to whole days, a whole number shape gives the Erlang distribution
+    Gamma { shape: f64, mean: f64 },
+    LogNormal { mu: f64, sigma: f64 },
+    // probability of each number of days from 0 upwards
+    Empirical { pmf: Vec<f64> }
+}
+
+#[derive(Clone, Debug)]
+pub enum SeedPeriods {
+    // initial cases start their infectious period from the beginning
+    Full,
+    // initial cases are part way through, as if the infection had been spreading for some time
+    Residual
+}
+
+impl PeriodDistribution {
+
+    pub fn from_file(file_path: &str) -> PeriodDistribution {
+        PeriodDistribution::Empirical { pmf: read_period_csv(file_path) }
+    }
+
+    pub fn pmf(&self, k: usize) -> f64 {
+        match self {
+            PeriodDistribution::Fixed { days } => if k == *days { 1.0 } else { 0.0 },
+            PeriodDistribution::Poisson { mean } => {
+                // statrs rejects a zero rate, which is a point mass at zero
+                if *mean > 0.0 { Poisson::new(*mean).unwrap().pmf(k as u64) } else if k == 0 { 1.0 } else { 0.0 }
+            },
+            PeriodDistribution::Gamma { shape, mean } => {
+                let gamma = Gamma::new(*shape, *shape / *mean).unwrap();
+                gamma.cdf((k + 1) as f64) - gamma.cdf(k as f64)
+            },
+            PeriodDistribution::LogNormal { mu, sigma } => {
+                let lognormal = LogNormal::new(*mu, *sigma).unwrap();
+                lognormal.cdf((k + 1) as f64) - lognormal.cdf(k as f64)
+            },
+            PeriodDistribution::Empirical { pmf } => {
+                let total: f64 = pmf.iter().sum();
+                pmf.get(k).map_or(0.0, |x| x / total)
+            }
+        }
+    }
+
+    pub fn pmf_vec(&self) -> Vec<f64> {
+        // probabilities from 0 until the remaining tail mass is negligible
+        match self {
+            PeriodDistribution::Fixed { days } => return (0..=*days).map(|k| self.pmf(k)).collect(),
+            PeriodDistribution::Empirical { pmf } => return (0..pmf.len()).map(|k| self.pmf(k)).collect(),
+            _ => ()
+        }
+        let mut pmf: Vec<f64> = Vec::new();
+        let mut total: f64 = 0.0;
+        while total < 1.0 - 1e-12 && pmf.len() < 100_000 {
+            let mass = self.pmf(pmf.len());
+            pmf.push(mass);
+            total += mass;
+        }
+        pmf
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.pmf_vec()
+            .iter()
+            .enumerate()
+            .map(|(k, p)| (k as f64)*p)
+            .sum()
+    }
+
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> usize {
+        match self {
+            PeriodDistribution::Fixed { days } => *days,
+            PeriodDistribution::Poisson { mean } => {
+                if *mean > 0.0 { rand_distr::Poisson::new(*mean).unwrap().sample(rng) as usize } else { 0 }
+            },
+            PeriodDistribution::Gamma { shape, mean } => {
+                rand_distr::Gamma::new(*shape, *mean / *shape).unwrap().sample(rng).floor() as usize
+            },
+            PeriodDistribution::LogNormal { mu, sigma } => {
+                rand_distr::LogNormal::new(*mu, *sigma).unwrap().sample(rng).floor() as usize
+            },
+            PeriodDistribution::Empirical { pmf } => sample_pmf(pmf, rng)
+        }
+    }
+
+    pub fn residual_pmf(&self) -> Vec<f64> {
+        // days left for a node found in the stage at a random time. Longer periods are more likely
+        // to be found in progress, which with a uniform position in the period gives P(D >= k)
+        let mut survival: f64 = 1.0;
+        self.pmf_vec()
+            .iter()
+            .map(|p| {
+                let x = survival;
+                survival -= p;
+                x.max(0.0)
+            })
+            .collect()
+    }
+
+    pub fn sample_residual<R: Rng>(&self, rng: &mut R) -> usize {
+        sample_pmf(&self.residual_pmf(), rng)
+    }
+}
+
+pub(crate) fn sample_pmf<R: Rng>(pmf: &[f64], rng: &mut R) -> usize {
+    // weights need not sum to one
+    let mut u: f64 = rng.gen::<f64>() * pmf.iter().sum::<f64>();
+    for (k, p) in pmf.iter().enumerate() {
+        if u < *p {
+            return k
+        }
+        u -= p;
+    }
+    pmf.len().saturating_sub(1)
+}
+
+impl NetworkProperties {
+    // the configured distributions, or Poisson latent and infectious periods with the means in
+    // parameters and a fixed immune period of parameters[3] days
+
+    pub fn latent_distribution(&self) -> PeriodDistribution {
+        self.latent_period.clone().unwrap_or(PeriodDistribution::Poisson { mean: self.parameters[1] })
+    }
+
+    pub fn infectious_distribution(&self) -> PeriodDistribution {
+        self.infectious_period.clone().unwrap_or(PeriodDistribution::Poisson { mean: self.parameters[2] })
+    }
+
+    pub fn immune_distribution(&self) -> PeriodDistribution {
+        self.immune_period.clone().unwrap_or(PeriodDistribution::Fixed { days: self.parameters[3] as usize })
+    }
+}
@@ -5,16 +5,31 @@ use std::vec;
 use nalgebra_sparse::coo::CooMatrix;
 extern crate random_choice;
 use self::random_choice::random_choice;
-use statrs::distribution::{Poisson, Geometric, NegativeBinomial};
+use statrs::distribution::NegativeBinomial;
+use rand_distr::{StandardNormal, WeightedAliasIndex};
+use std::collections::HashSet;
+use std::error::Error;
 use rand::prelude::*;
+use rand::rngs::StdRng;
 use serde::Serialize;
+use crate::vaccination::VaccineStatus;
+use crate::natural_history::NaturalHistory;
+use crate::periods::{PeriodDistribution, SeedPeriods};
+use crate::seeding::{SeedState, SeedingError};
+use crate::importation::{Importation, InfectionSource};
+use crate::infectiousness::InfectiousnessProfile;
+use crate::seasonality::TransmissionSchedule;
 
 #[derive(Clone,Debug)]
 pub enum State {
     Susceptible,
     Exposed(usize),
     Infected(usize),
-    Recovered(usize)
+    Recovered(usize),
+    // stages of the extended natural history, in place of Infected
+    Presymptomatic(usize),
+    Symptomatic(usize),
+    Asymptomatic(usize)
 }
 
 #[derive(Clone)]
@@ -31,11 +46,36 @@ pub enum OutbreakType {
     SEIRS
 }
 
-#[derive(Debug)]
+#[derive(Clone)]
+pub enum TransmissionMode {
+    // every edge transmits with parameters[0] per day whatever its weight
+    Unweighted,
+    // weights are contact durations, transmitting with 1-(1-p)^w per day
+    Duration,
+    // weights are the probability that the contact happens on a given day
+    Frequency
+}
+
+#[derive(Clone,Debug)]
+pub enum WeightDistribution {
+    Constant,
+    Gamma { shape: f64 },
+    LogNormal { sigma: f64 },
+    // one plus a negative binomial count with mean mu and dispersion k
+    NegativeBinomial { mu: f64, k: f64 }
+}
+
+#[derive(Clone,Debug)]
 pub struct NetworkStructure {
     pub adjacency_matrix: CooMatrix<f64>,
     pub degree: Vec<f64>,
-    pub age_brackets: Vec<usize>
+    pub age_brackets: Vec<usize>,
+    // household id of each node, empty until a household layer is added
+    pub household: Vec<usize>,
+    // names of the contact layers, empty for a single unnamed layer
+    pub layers: Vec<String>,
+    // layer id of each entry of the adjacency matrix, whose values hold the edge weights
+    pub edge_layer: Vec<usize>
 }
 
 #[derive(Clone)]
@@ -45,7 +85,43 @@ pub struct NetworkProperties {
     pub result_type: ResultType,
     pub outbreak_type: OutbreakType,
     pub parameters: Vec<f64>,
-    pub secondary_cases: Vec<usize>
+    pub secondary_cases: Vec<usize>,
+    // transmission multiplier for each layer id, missing layers transmit at the base rate
+    pub layer_multipliers: Vec<f64>,
+    pub transmission_mode: TransmissionMode,
+    // contact multiplier of each node and of each adjacency matrix entry, empty when unchanged
+    pub node_multipliers: Vec<f64>,
+    pub edge_multipliers: Vec<f64>,
+    // doses and protection of each node, empty without a vaccination campaign
+    pub vaccine_status: Vec<VaccineStatus>,
+    // days of quarantine left for each node, whose contacts do not transmit meanwhile
+    pub quarantine: Vec<usize>,
+    // who infected each node, and the day, infector and infectee of every transmission
+    pub infected_by: Vec<Option<usize>>,
+    pub transmissions: Vec<(usize, InfectionSource, usize)>,
+    // runs with the same seed draw the same random numbers, for paired comparisons
+    pub seed: Option<u64>,
+    // presymptomatic, symptomatic and asymptomatic stages, None for a single infected state
+    pub natural_history: Option<NaturalHistory>,
+    // distributions of the latent, infectious and immune periods, None for the defaults from parameters
+    pub latent_period: Option<PeriodDistribution>,
+    pub infectious_period: Option<PeriodDistribution>,
+    pub immune_period: Option<PeriodDistribution>,
+    pub seed_periods: SeedPeriods,
+    // infectiousness by days since becoming infectious, None for the same on every day
+    pub infectiousness_profile: Option<InfectiousnessProfile>,
+    // day each node was last infected, and the day it became infectious
+    pub infection_day: Vec<Option<usize>>,
+    pub infectious_day: Vec<Option<usize>>,
+    // relative susceptibility and infectiousness of each age bracket and of each node, empty when all the same
+    pub age_susceptibility: Vec<f64>,
+    pub age_infectiousness: Vec<f64>,
+    pub node_susceptibility: Vec<f64>,
+    pub node_infectiousness: Vec<f64>,
+    // multiplier of transmission by day, None for the same on every day
+    pub transmission_schedule: Option<TransmissionSchedule>,
+    // infections from outside the network during the run
+    pub importation: Option<Importation>
 }
 
 #[derive(Debug,Serialize)]
@@ -53,50 +129,92 @@ pub struct Output {
     pub seir: Vec<Vec<usize>>,
     pub infections: Vec<Vec<usize>>,
     pub network_struct: SerializeableNetwork,
-    pub secondary_cases: Vec<Vec<usize>>
+    pub secondary_cases: Vec<Vec<usize>>,
+    // day and description of anything that changed during the run, such as interventions
+    pub events: Vec<(usize, String)>,
+    pub vaccine_status: Vec<VaccineStatus>,
+    pub doses_delivered: Vec<usize>,
+    pub infected_by: Vec<Option<usize>>,
+    pub transmissions: Vec<(usize, InfectionSource, usize)>,
+    // number of people in quarantine on each day
+    pub quarantined: Vec<usize>,
+    // transmissions between nodes in a run without the intervention from the same seed and initial
+    // infections, less those in this run, and 0 if there were fewer. The runs are not otherwise
+    // matched, so this varies between seeds
+    pub infections_prevented: isize,
+    // daily hospital admissions, beds and ICU beds in use, and deaths
+    pub admissions: Vec<usize>,
+    pub hospital_occupancy: Vec<usize>,
+    pub icu_occupancy: Vec<usize>,
+    pub deaths: Vec<usize>,
+    pub infection_day: Vec<Option<usize>>,
+    pub infectious_day: Vec<Option<usize>>
 }
 
 impl NetworkStructure {
 
     pub fn new_molloy_reed(n: usize, partitions: Vec<usize>, file_path: &str) -> NetworkStructure {     
+        // import parameters to sample
+        let dist_params = read_params_json(file_path);
+        let group_sizes = group_sizes(&partitions);
+        // every node draws its contacts with each age bracket independently
+        let samplers = dist_params.samplers().unwrap();
+        let sample_degrees = |i: usize, j: usize, rng: &mut ThreadRng| -> Vec<usize> {
+            (0..group_sizes[i])
+                .map(|_| samplers[i][j].sample(rng))
+                .collect()
+        };
+        NetworkStructure::match_block_degrees(n, partitions, sample_degrees)
+    }
+
+    pub fn new_molloy_reed_correlated(n: usize, partitions: Vec<usize>, file_path: &str, activity_correlation: &[f64]) -> Result<NetworkStructure, Box<dyn Error>> {
+        // each node has a latent activity level shared by its contacts with all age brackets,
+        // activity_correlation[i] sets how strongly the brackets co-vary for nodes in bracket i
+        let dist_params = read_params_json(file_path);
+        if activity_correlation.len() < partitions.len() {
+            return Err(format!("{} age brackets but only {} activity correlations", partitions.len(), activity_correlation.len()).into())
+        }
+        if dist_params.blocks() < partitions.len() {
+            return Err(format!("{} age brackets but only {} in {}", partitions.len(), dist_params.blocks(), file_path).into())
+        }
+        dist_params.samplers()?;
+        let cdfs: Vec<Vec<Vec<f64>>> = (0..partitions.len())
+            .map(|i| {
+                (0..partitions.len()).map(|j| dist_params.degree_cdf(i, j)).collect()
+            })
+            .collect();
+        if cdfs.iter().flatten().any(|cdf| cdf.is_empty()) {
+            return Err(format!("a degree distribution in {} has no probability mass", file_path).into())
+        }
+        let group_sizes = group_sizes(&partitions);
+        let mut rng: ThreadRng = rand::thread_rng();
+        let activity: Vec<f64> = (0..n).map(|_| rng.sample(StandardNormal)).collect();
+        let sample_degrees = |i: usize, j: usize, rng: &mut ThreadRng| -> Vec<usize> {
+            let start = partitions[i] - group_sizes[i];
+            activity[start..partitions[i]]
+                .iter()
+                .map(|&z| correlated_degree(&cdfs[i][j], z, activity_correlation[i], rng))
+                .collect()
+        };
+        Ok(NetworkStructure::match_block_degrees(n, partitions.clone(), sample_degrees))
+    }
+
+    fn match_block_degrees<F>(n: usize, partitions: Vec<usize>, mut sample_degrees: F) -> NetworkStructure
+    where
+        F: FnMut(usize, usize, &mut ThreadRng) -> Vec<usize>
+    {
         let mut rng: ThreadRng = rand::thread_rng();
         let mut coo_mat: CooMatrix<f64> = CooMatrix::new(n,n);
         let mut degrees: Vec<f64> = vec![0.0;n];
-        let mut count: usize = 0;
         // calculate group sizes
-        let mut group_sizes: Vec<usize> = partitions
-            .windows(2)
-            .map(|pair| {
-                pair[1] - pair[0]
-            })
-            .collect();
-        group_sizes.insert(0,partitions[0]);
-        // import parameters to sample
-        let dist_params = read_params_json(file_path);
+        let group_sizes = group_sizes(&partitions);
         
         // start iteration through age brackets, take(i+1) makes loop run over lower diag to remove double counting
         for (i, x) in partitions.iter().enumerate() {
             for (j, y) in partitions.iter().enumerate().take(i+1) {
-                // create distributions and sample degrees from this 
-                let poisson = Poisson::new(dist_params.lambda[i][j]);
-                let geometric = Geometric::new(dist_params.p_geom[i][j]);
-                let p = dist_params.p[i][j];
-                let mut out_degree: Vec<usize> = (0..group_sizes[i])
-                    .map(|_| {
-                        (p*poisson.as_ref().unwrap().sample(&mut rng) + (1.0-p)*geometric.as_ref().unwrap().sample(&mut rng)) as usize
-                    }
-                    )
-                    .collect();
-                // repeat for in degrees
-                let poisson = Poisson::new(dist_params.lambda[j][i]);
-                let geometric = Geometric::new(dist_params.p_geom[j][i]);
-                let p = dist_params.p[j][i];
-                let mut in_degree: Vec<usize> = (0..group_sizes[j])
-                    .map(|_| {
-                        (p*poisson.as_ref().unwrap().sample(&mut rng) + (1.0-p)*geometric.as_ref().unwrap().sample(&mut rng)) as usize
-                    }
-                    )
-                    .collect();
+                // sample out degrees of bracket i towards j, then the in degrees of bracket j
+                let mut out_degree: Vec<usize> = sample_degrees(i, j, &mut rng);
+                let mut in_degree: Vec<usize> = sample_degrees(j, i, &mut rng);
                 
                 // loop over out degrees and match with the in nodes
                 let mut repeats: Vec<(usize,usize)> = Vec::new();
@@ -105,27 +223,17 @@ impl NetworkStructure {
                     if i == j {
                         in_degree[node_i] = 0;
                     }
-                    count = 0;
+                    let mut count: usize = 0;
                     let mut connections: Vec<usize> = in_degree
                         .iter()
                         .enumerate()
                         .filter(|(idx, x)| {
-                            // stop if passed out degree, and in degree must be non-zero
-                            if count < out_degree[node_i] && **x > 0 {
-                                // check if link already created
-                                if *idx >= node_i {
-                                    count += 1;
-                                    return true
-                                }
-                                else if !repeats.contains(&(*idx, node_i)) {
-                                    count += 1;
-                                    return true
-                                }
-                                else {
-                                    return false
-                                }
+                            // stop if passed out degree, in degree must be non-zero and the link must not already exist
+                            if count < out_degree[node_i] && **x > 0 && (*idx >= node_i || !repeats.contains(&(*idx, node_i))) {
+                                count += 1;
+                                return true
                             }
-                            return false
+                            false
                         })
                         .map(|(i,_)| i)
                         .collect();
@@ -160,7 +268,10 @@ impl NetworkStructure {
         NetworkStructure {
             adjacency_matrix: coo_mat,
             degree: degrees,
-            age_brackets: ages
+            age_brackets: ages,
+            household: Vec::new(),
+            layers: Vec::new(),
+            edge_layer: Vec::new()
         }
     }
 
@@ -197,7 +308,10 @@ impl NetworkStructure {
         NetworkStructure {
             adjacency_matrix: coo_mat,
             degree: degrees,
-            age_brackets: Vec::new()
+            age_brackets: Vec::new(),
+            household: Vec::new(),
+            layers: Vec::new(),
+            edge_layer: Vec::new()
         }
     }
 
@@ -256,90 +370,187 @@ impl NetworkStructure {
         NetworkStructure {
             adjacency_matrix: coo_mat,
             degree: degrees,
-            age_brackets: ages
+            age_brackets: ages,
+            household: Vec::new(),
+            layers: Vec::new(),
+            edge_layer: Vec::new()
         }
     }
 
-    pub fn new_sbm_weighted(n: usize, partitions: Vec<usize>, rates_mat: Vec<Vec<f64>>) -> NetworkStructure {
-        // unfinished weighting step !!
-        // find consecutive group sizes to turn rates to probabilities
-        let mut group_sizes: Vec<usize> = partitions
-            .windows(2)
-            .map(|pair| {
-                pair[1] - pair[0]
+    pub fn new_sbm_weighted(n: usize, partitions: Vec<usize>, rates_mat: Vec<Vec<f64>>, weight_distribution: WeightDistribution) -> NetworkStructure {
+        // degree corrected SBM, node weights are normalised to mean one within each age bracket
+        // so the expected rates between brackets are still those of rates_mat
+        let prob_mat: Vec<Vec<f64>> = rates_to_probabilities(rates_mat, &partitions);
+        let group_sizes = group_sizes(&partitions);
+        let mut rng: ThreadRng = rand::thread_rng();
+        let mut weights: Vec<f64> = (0..n).map(|_| weight_distribution.sample(&mut rng)).collect();
+        for (i, x) in partitions.iter().enumerate() {
+            let block = &mut weights[(*x - group_sizes[i])..*x];
+            let mean = block.iter().sum::<f64>() / (block.len() as f64);
+            if mean > 0.0 {
+                block.iter_mut().for_each(|w| *w /= mean);
+            }
+        }
+
+        let mut coo_mat: CooMatrix<f64> = CooMatrix::new(n,n);
+        let mut degrees: Vec<f64> = vec![0.0;n];
+        let mut edges: HashSet<(usize, usize)> = HashSet::new();
+        // loop through lower triangular blocks, drawing the number of edges in each and then
+        // their ends in proportion to the weights, so generation is linear in the edges
+        for (i, x) in partitions.iter().enumerate() {
+            for (j, y) in partitions.iter().enumerate().take(i+1) {
+                let (start_i, start_j) = (*x - group_sizes[i], *y - group_sizes[j]);
+                let pairs = if i == j {
+                    (group_sizes[i] as f64) * (group_sizes[i] as f64 - 1.0) / 2.0
+                } else {
+                    (group_sizes[i] as f64) * (group_sizes[j] as f64)
+                };
+                let expected_edges = pairs * prob_mat[i][j];
+                if expected_edges <= 0.0 {
+                    continue;
+                }
+                // a block cannot hold more edges than it has pairs
+                let num_edges = (rand_distr::Poisson::new(expected_edges).unwrap().sample(&mut rng) as usize).min(pairs as usize);
+                let choose_i = WeightedAliasIndex::new(weights[start_i..*x].to_vec());
+                let choose_j = WeightedAliasIndex::new(weights[start_j..*y].to_vec());
+                let (choose_i, choose_j) = match (choose_i, choose_j) {
+                    (Ok(choose_i), Ok(choose_j)) => (choose_i, choose_j),
+                    _ => continue
+                };
+                // self loops and multi-edges are redrawn so the block keeps its poisson number of
+                // edges, giving up only if nearly every pair is already taken by heavy nodes
+                let mut placed: usize = 0;
+                let mut attempts: usize = 0;
+                while placed < num_edges && attempts < 100*num_edges {
+                    attempts += 1;
+                    let node_i = start_i + choose_i.sample(&mut rng);
+                    let node_j = start_j + choose_j.sample(&mut rng);
+                    // stored with the larger index first like new_sbm
+                    let edge = (node_i.max(node_j), node_i.min(node_j));
+                    if node_i == node_j || !edges.insert(edge) {
+                        continue;
+                    }
+                    placed += 1;
+                    coo_mat.push(edge.0, edge.1, 1.0);
+                    coo_mat.push(edge.1, edge.0, 1.0);
+                    degrees[edge.0] += 1.0;
+                    degrees[edge.1] += 1.0;
+                }
+            }
+        }
+        
+        // define ages from partitioning and adjacency matrix as Csr mat
+        let mut last_idx = 0;
+        let ages: Vec<usize> = partitions  
+            .iter()
+            .enumerate()
+            .flat_map(|(i,x)| {
+                let answer = vec![i; *x - last_idx];
+                last_idx = *x;
+                answer
             })
             .collect();
-        group_sizes.insert(0,partitions[0]);
-        // transform rates matrix to probability matrix 
-        let prob_mat: Vec<Vec<f64>> = rates_mat
+
+        // ensure ages and degree length is consistent
+        assert_eq!(ages.len(), degrees.len()); 
+
+        NetworkStructure {
+            adjacency_matrix: coo_mat,
+            degree: degrees,
+            age_brackets: ages,
+            household: Vec::new(),
+            layers: Vec::new(),
+            edge_layer: Vec::new()
+        }
+    }
+
+    pub fn new_chung_lu(n: usize, partitions: Vec<usize>, expected_degrees: Vec<f64>, mixing_mat: Vec<Vec<f64>>) -> NetworkStructure {
+        // expected degree (Chung-Lu) graph with age brackets, mixing_mat[a][b] is the relative
+        // share of the contacts of bracket a that are with bracket b
+        let group_sizes = group_sizes(&partitions);
+        let blocks = partitions.len();
+        let block_weights: Vec<f64> = partitions
             .iter()
             .enumerate()
-            .map(|(i, row)| {
-                row.iter().map(|rate| {
-                    rate / (group_sizes[i] as f64)
-                })
-                .collect()
+            .map(|(i, x)| expected_degrees[(*x - group_sizes[i])..*x].iter().sum())
+            .collect();
+        // expected contacts between each pair of brackets, fitted so both sides of every edge
+        // agree and each bracket still has the sum of its nodes' expected degrees
+        let block_edges: Vec<Vec<f64>> = balance_mixing(&mixing_mat, &block_weights);
+        // nodes of each bracket in decreasing order of expected degree for the skipping algorithm
+        let sorted_nodes: Vec<Vec<usize>> = partitions
+            .iter()
+            .enumerate()
+            .map(|(i, x)| {
+                let mut nodes: Vec<usize> = ((*x - group_sizes[i])..*x).collect();
+                nodes.sort_by(|u, v| expected_degrees[*v].total_cmp(&expected_degrees[*u]));
+                nodes
             })
             .collect();
-        // finish this, do weight calculation here from NB, mu = 4.79, k = 0.54
-        let mu: f64 = 4.79; let k: f64 = 0.54;
+
         let mut rng: ThreadRng = rand::thread_rng();
-        let weights: Vec<f64> = NegativeBinomial::new(k, k/(k+mu))
-            .unwrap()
-            .sample_iter(&mut rng)
-            .take(n)
-            .map(|x| (x as f64) + 1.0)
-            .collect();
-        
         let mut coo_mat: CooMatrix<f64> = CooMatrix::new(n,n);
         let mut degrees: Vec<f64> = vec![0.0;n];
-        // loop through partitions and then individuals
-        let mut last_idx: [usize;2] = [0,0];
-        let mut rand_num: f64;
-        
-        // loop through lower triangular
-        let mut part_i: usize; let mut part_j: usize;
-        for i in 0..n {
-            for j in 0..i {
-                // find which block we are in
-                part_i = partitions
-                    .iter()
-                    .position(|&x| (i/x) < 1)
-                    .unwrap();
-                part_j = partitions
-                    .iter()
-                    .position(|&x| (j/x) < 1)
-                    .unwrap();
-                // randomly generate edges with probability prob_mat
-                rand_num = rng.gen();
-                if rand_num < prob_mat[part_i][part_j] * weights[i] * weights[j] {
-                    coo_mat.push(i, j, 1.0);
-                    coo_mat.push(j, i, 1.0);
-                    degrees[i] += 1.0;
-                    degrees[j] += 1.0;
+        // Miller-Hagberg, skip ahead geometrically over candidate neighbours so that the
+        // work is linear in the number of nodes and edges
+        for a in 0..blocks {
+            for b in 0..=a {
+                if block_weights[a] <= 0.0 || block_weights[b] <= 0.0 {
+                    continue;
+                }
+                // edge probability is scale * w_u * w_v, within a bracket block_edges counts
+                // both ends of each edge and each pair is only visited once
+                let scale = block_edges[a][b] / (block_weights[a] * block_weights[b]);
+                let (nodes_a, nodes_b) = (&sorted_nodes[a], &sorted_nodes[b]);
+                for (idx, &u) in nodes_a.iter().enumerate() {
+                    let mut j = if a == b { idx + 1 } else { 0 };
+                    if j >= nodes_b.len() {
+                        continue;
+                    }
+                    let mut p = (scale * expected_degrees[u] * expected_degrees[nodes_b[j]]).min(1.0);
+                    while j < nodes_b.len() && p > 0.0 {
+                        if p < 1.0 {
+                            let r: f64 = rng.gen();
+                            j = j.saturating_add((r.ln() / (1.0 - p).ln()).floor() as usize);
+                        }
+                        if j < nodes_b.len() {
+                            let v = nodes_b[j];
+                            let q = (scale * expected_degrees[u] * expected_degrees[v]).min(1.0);
+                            let r: f64 = rng.gen();
+                            // accept with the ratio of the true probability to the one skipped with
+                            if r < q / p {
+                                coo_mat.push(u, v, 1.0);
+                                coo_mat.push(v, u, 1.0);
+                                degrees[u] += 1.0;
+                                degrees[v] += 1.0;
+                            }
+                            p = q;
+                            j += 1;
+                        }
+                    }
                 }
             }
         }
-        
-        // define ages from partitioning and adjacency matrix as Csr mat
-        last_idx[0] = 0;
+
+        // define ages from partitioning
+        let mut last_idx = 0;
         let ages: Vec<usize> = partitions  
             .iter()
             .enumerate()
             .flat_map(|(i,x)| {
-                let answer = vec![i; *x - last_idx[0]];
-                last_idx[0] = *x;
+                let answer = vec![i; *x - last_idx];
+                last_idx = *x;
                 answer
             })
             .collect();
 
-        // ensure ages and degree length is consistent
-        assert_eq!(ages.len(), degrees.len()); 
-
         NetworkStructure {
             adjacency_matrix: coo_mat,
             degree: degrees,
-            age_brackets: ages
+            age_brackets: ages,
+            household: Vec::new(),
+            layers: Vec::new(),
+            edge_layer: Vec::new()
         }
     }
 }
@@ -353,7 +564,30 @@ impl NetworkProperties {
             result_type: ResultType::SEIR,
             outbreak_type: OutbreakType::SIR,
             parameters: vec![0.1,0.2],
-            secondary_cases: vec![0; network.degree.len()]
+            secondary_cases: vec![0; network.degree.len()],
+            layer_multipliers: Vec::new(),
+            transmission_mode: TransmissionMode::Unweighted,
+            node_multipliers: Vec::new(),
+            edge_multipliers: Vec::new(),
+            vaccine_status: Vec::new(),
+            quarantine: Vec::new(),
+            infected_by: vec![None; network.degree.len()],
+            transmissions: Vec::new(),
+            seed: None,
+            natural_history: None,
+            latent_period: None,
+            infectious_period: None,
+            immune_period: None,
+            seed_periods: SeedPeriods::Full,
+            infectiousness_profile: None,
+            infection_day: vec![None; network.degree.len()],
+            infectious_day: vec![None; network.degree.len()],
+            age_susceptibility: Vec::new(),
+            age_infectiousness: Vec::new(),
+            node_susceptibility: Vec::new(),
+            node_infectiousness: Vec::new(),
+            transmission_schedule: None,
+            importation: None
         }
     }
 
@@ -377,44 +611,90 @@ impl NetworkProperties {
         }
     }
 
-    pub fn initialize_infection(&mut self, proportion_of_population: f64) {
-        let number_of_infecteds: usize = match proportion_of_population as usize {
-            0..=1 => {
-                ((self.nodal_states.len() as f64) * proportion_of_population) as usize
-            },
-            _ => {
-                println!("The proportion infected must be between 0 and 1");
-                0
-            }
-        };
-        // define random number generator
-        let mut rng = rand::thread_rng();
-        // shuffle indices and choose
+    pub fn initialize_infection(&mut self, proportion_of_population: f64) -> Result<usize, SeedingError> {
+        // returns the number of nodes infected
+        if !(0.0..=1.0).contains(&proportion_of_population) {
+            return Err(SeedingError::InvalidProportion(proportion_of_population))
+        }
+        let number_of_infecteds = ((self.nodal_states.len() as f64) * proportion_of_population) as usize;
+        // a stream of its own, the generator of the run starts afresh from rng()
+        let mut rng = self.rng_stream(3);
+        // shuffle indices and choose, leaving any nodes already seeded or immune
         let mut indices: Vec<usize> = (0..self.nodal_states.len()).collect();
         indices.shuffle(&mut rng);
-        for i in 0..number_of_infecteds {
-            self.nodal_states[indices[i]] = State::Infected(0)
-        }
+        let indices: Vec<usize> = indices
+            .into_iter()
+            .filter(|&i| matches!(self.nodal_states[i], State::Susceptible))
+            .take(number_of_infecteds)
+            .collect();
+        self.seed_nodes(&indices, &SeedState::Infectious, 0, &mut rng);
         self.results.push(self.count_states());
+        Ok(indices.len())
+    }
+
+    pub fn rng_stream(&self, stream: u64) -> StdRng {
+        // a seeded generator other than the one of the run, for draws made before it. Seed and
+        // stream are mixed, so the streams of neighbouring seeds, e.g. in an ensemble, differ
+        match self.seed {
+            Some(seed) => StdRng::seed_from_u64(splitmix64(splitmix64(seed).wrapping_add(stream))),
+            None => StdRng::from_entropy()
+        }
+    }
+
+    pub fn rng(&self) -> StdRng {
+        // the generator of a run, created once by each runner and passed to every step
+        self.rng_stream(0)
     }
 
     pub fn count_states(&self) -> Vec<usize> {
-        let mut result: Vec<usize> = vec![0; 4];
+        // S, E, I, R, where I counts every infectious stage, followed by the presymptomatic,
+        // symptomatic and asymptomatic counts when the extended natural history is used
+        let columns = if self.natural_history.is_some() { 7 } else { 4 };
+        let mut result: Vec<usize> = vec![0; columns];
         for state in self.nodal_states.iter() {
             match state {
                 State::Susceptible => result[0] += 1,
                 State::Exposed(_) => result[1] += 1,
                 State::Infected(_) => result[2] += 1,
-                State::Recovered(_) => result[3] += 1
+                State::Recovered(_) => result[3] += 1,
+                State::Presymptomatic(_) => {
+                    result[2] += 1;
+                    result[4] += 1;
+                },
+                State::Symptomatic(_) => {
+                    result[2] += 1;
+                    result[5] += 1;
+                },
+                State::Asymptomatic(_) => {
+                    result[2] += 1;
+                    result[6] += 1;
+                }
             }
         }
         result
     }
 }
 
+impl WeightDistribution {
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> f64 {
+        // scale is irrelevant as weights are normalised within each age bracket
+        match self {
+            WeightDistribution::Constant => 1.0,
+            WeightDistribution::Gamma { shape } => rand_distr::Gamma::new(*shape, 1.0).unwrap().sample(rng),
+            WeightDistribution::LogNormal { sigma } => rand_distr::LogNormal::new(0.0, *sigma).unwrap().sample(rng),
+            WeightDistribution::NegativeBinomial { mu, k } => {
+                (NegativeBinomial::new(*k, k/(k+mu)).unwrap().sample(rng) as f64) + 1.0
+            }
+        }
+    }
+}
+
 impl Output {
     pub fn new() -> Output {
-        Output { seir: Vec::new(), infections: Vec::new(), network_struct: SerializeableNetwork::new(), secondary_cases: Vec::new() }
+        Output { seir: Vec::new(), infections: Vec::new(), network_struct: SerializeableNetwork::new(), secondary_cases: Vec::new(), events: Vec::new(), vaccine_status: Vec::new(), doses_delivered: Vec::new(),
+            infected_by: Vec::new(), transmissions: Vec::new(), quarantined: Vec::new(), infections_prevented: 0,
+            admissions: Vec::new(), hospital_occupancy: Vec::new(), icu_occupancy: Vec::new(), deaths: Vec::new(),
+            infection_day: Vec::new(), infectious_day: Vec::new() }
     }
 }
 
@@ -424,7 +704,10 @@ pub struct SerializeableNetwork {
     col_idx: Vec<usize>,
     values: Vec<f64>,
     ages: Vec<usize>,
-    degrees: Vec<f64>
+    degrees: Vec<f64>,
+    households: Vec<usize>,
+    layers: Vec<String>,
+    edge_layer: Vec<usize>
 }
 
 impl SerializeableNetwork {
@@ -435,7 +718,10 @@ impl SerializeableNetwork {
             col_idx: Vec::new(), 
             values: Vec::new(), 
             ages: Vec::new(), 
-            degrees: Vec::new() 
+            degrees: Vec::new(),
+            households: Vec::new(),
+            layers: Vec::new(),
+            edge_layer: Vec::new()
         }
     }
 
@@ -446,7 +732,18 @@ impl SerializeableNetwork {
             col_idx: coo_mat.col_indices().iter().map(|&x| x).collect(),
             values: coo_mat.values().iter().map(|&x| x).collect(),
             ages: network_structure.age_brackets.clone(),
-            degrees: network_structure.degree.clone()
+            degrees: network_structure.degree.clone(),
+            households: network_structure.household.clone(),
+            layers: network_structure.layers.clone(),
+            edge_layer: network_structure.edge_layer.clone()
         }
     }
-}
\ No newline at end of file
+}
+
+fn splitmix64(x: u64) -> u64 {
+    // the output function of the SplitMix64 generator, which scatters nearby inputs
+    let mut z = x.wrapping_add(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
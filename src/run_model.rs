@@ -1,6 +1,9 @@
 use crate::random_graphs::*;
-use rand::{rngs::ThreadRng, Rng};
-use rand_distr::{Distribution, Poisson};
+use rand::Rng;
+use rand::rngs::StdRng;
+use crate::importation::InfectionSource;
+use crate::periods::PeriodDistribution;
+use crate::seeding::{SeedState, SeedingError};
 
 // pub fn run_model_parallel(network_structure: &NetworkStructure, network_properties: &NetworkProperties, maxtime: f64, dt: f64, initially_infected: f64) -> Output {
 //     let iterations: Vec<usize> = match network_properties.result_type {
@@ -25,45 +28,57 @@ use rand_distr::{Distribution, Poisson};
 //     Arc::try_unwrap(output).unwrap().into_inner().unwrap() // Unwrap the `Mutex` and return the `Output`
 // }
 
-pub fn run_model(network_structure: &NetworkStructure, network_properties: &mut NetworkProperties, maxtime: f64, initially_infected: f64) -> Output {
-    network_properties.initialize_infection(initially_infected);
-    let mut rng = rand::thread_rng();
+pub fn run_model(network_structure: &NetworkStructure, network_properties: &mut NetworkProperties, maxtime: f64, initially_infected: f64) -> Result<Output, SeedingError> {
+    run_days(network_properties, maxtime, initially_infected, |network_properties, _, rng| {
+        step_model(network_structure, network_properties, rng)
+    })
+}
+
+pub(crate) fn run_days<F>(network_properties: &mut NetworkProperties, maxtime: f64, initially_infected: f64, mut step_day: F) -> Result<Output, SeedingError>
+where
+    F: FnMut(&mut NetworkProperties, usize, &mut StdRng)
+{
+    // the loop of every runner, step_day simulates the given day on whichever network is current
+    network_properties.initialize_infection(initially_infected)?;
+    let mut rng = network_properties.rng();
     for i in 0..(maxtime as usize) {
-        step_model(&network_structure, network_properties, &mut rng);
+        step_day(network_properties, i, &mut rng);
         if i % 10 == 0 {
             println!("{i}");
         }
-        if network_properties.results.last().unwrap()[1] + network_properties.results.last().unwrap()[2] == 0 {
+        if network_properties.outbreak_over() {
             break;
         }
     }
+    Ok(collect_output(network_properties))
+}
+
+pub(crate) fn collect_output(network_properties: &NetworkProperties) -> Output {
     // matching the measures wanted from
     let mut output: Output = Output::new(); 
+    output.vaccine_status = network_properties.vaccine_status.clone();
+    output.infected_by = network_properties.infected_by.clone();
+    output.transmissions = network_properties.transmissions.clone();
+    output.infection_day = network_properties.infection_day.clone();
+    output.infectious_day = network_properties.infectious_day.clone();
     match network_properties.result_type {
         ResultType::SEIR => {
             output.seir = network_properties.results.clone();
         },
         ResultType::AvgInfections(_) => {
-            let mut infections: Vec<Vec<usize>> = Vec::new();
-            infections.push(
+            output.infections = vec![
                 network_properties.results
                     .iter()
                     .map(|x| x[1])
                     .collect()
-            );
-            output.infections = infections;
+            ];
         },
         ResultType::SecondaryCases(_) => {
             output.secondary_cases.push(
                 network_properties.secondary_cases
                     .iter()
                     .enumerate()
-                    .filter(|(i, _)| {
-                        match network_properties.nodal_states[*i] {
-                            State::Recovered(_) => true,
-                            _ => false
-                        }
-                    })
+                    .filter(|(i, _)| matches!(network_properties.nodal_states[*i], State::Recovered(_)))
                     .map(|(_, x)| *x)
                     .collect()
             );
@@ -72,53 +87,118 @@ pub fn run_model(network_structure: &NetworkStructure, network_properties: &mut
     output
 }
 
-fn step_model(network_structure: &NetworkStructure, network_properties: &mut NetworkProperties, rng: &mut ThreadRng) {
+pub(crate) fn step_model<R: Rng>(network_structure: &NetworkStructure, network_properties: &mut NetworkProperties, rng: &mut R) {
     let mut next_states: Vec<State> = vec![State::Susceptible; network_structure.degree.len()];
-    let poisson_infectious_period = Poisson::new(network_properties.parameters[2]).unwrap();
-    let poisson_exposed_period = Poisson::new(network_properties.parameters[1]).unwrap();
+    let latent_period = network_properties.latent_distribution();
+    let infectious_period = network_properties.infectious_distribution();
+    let immune_period = network_properties.immune_distribution();
+    let neighbours = network_structure.neighbours();
+    let day = network_properties.results.len() - 1;
+    let presymptomatic_period = network_properties.natural_history
+        .as_ref()
+        .map(|history| PeriodDistribution::Poisson { mean: history.presymptomatic_period });
+    let profile: Option<Vec<f64>> = network_properties.infectiousness_profile.as_ref().map(|x| x.values());
+    let mut infectious_onsets: Vec<usize> = Vec::new();
+    let seasonal = network_properties.transmission_multiplier(day);
     for (i, state) in network_properties.nodal_states.iter().enumerate() {
         match *state {
             State::Susceptible => (),
             State::Exposed(days) => {
-                if days <= 0 {
-                    next_states[i] = State::Infected(poisson_infectious_period.sample(rng) as usize);
+                if days == 0 {
+                    next_states[i] = match &presymptomatic_period {
+                        Some(period) => State::Presymptomatic(period.sample(rng)),
+                        None => State::Infected(infectious_period.sample(rng))
+                    };
+                    infectious_onsets.push(i);
                 }
                 else {
                     next_states[i] = State::Exposed(days - 1);
                 }
             },
-            State::Infected(days) => {
-                if days <= 0 {
-                    next_states[i] = State::Recovered(0);
+            State::Presymptomatic(days) => {
+                if days == 0 {
+                    // symptoms or not, by the age specific chance of staying asymptomatic
+                    let history = network_properties.natural_history.as_ref().unwrap();
+                    let period = infectious_period.sample(rng);
+                    if rng.gen::<f64>() < history.asymptomatic_probability(network_structure.age_brackets.get(i).copied().unwrap_or(0)) {
+                        next_states[i] = State::Asymptomatic(period);
+                    }
+                    else {
+                        next_states[i] = State::Symptomatic(period);
+                    }
                 }
                 else {
-                    next_states[i] = State::Infected(days - 1);
+                    next_states[i] = State::Presymptomatic(days - 1);
                 }
-                // find connections to infected individuals
-                let connections: Vec<usize> = network_structure.adjacency_matrix
-                    .triplet_iter()
-                    .filter(|&(row, _, _)| row == i)
-                    .map(|(_, j, _)| j)
-                    .collect();
-                for j in connections.iter() {
-                    match network_properties.nodal_states[*j] {
-                        State::Susceptible => {
-                            if rng.gen::<f64>() < network_properties.parameters[0] {
-                                next_states[*j] = State::Exposed(poisson_exposed_period.sample(rng) as usize);
-                                network_properties.secondary_cases[i] += 1;
-                            }
-                        },
-                        _ => ()
-                    }
+            },
+            State::Infected(days) | State::Symptomatic(days) | State::Asymptomatic(days) => {
+                if days == 0 {
+                    next_states[i] = State::Recovered(immune_period.sample(rng));
+                }
+                else {
+                    next_states[i] = match *state {
+                        State::Symptomatic(_) => State::Symptomatic(days - 1),
+                        State::Asymptomatic(_) => State::Asymptomatic(days - 1),
+                        _ => State::Infected(days - 1)
+                    };
                 }
             },
             State::Recovered(days) => {
-                if days < network_properties.parameters[3] as usize {
-                    next_states[i] = State::Recovered(days + 1);
+                // susceptible again once immunity runs out
+                if days > 0 {
+                    next_states[i] = State::Recovered(days - 1);
+                }
+            }
+        }
+        if state.infectious() {
+            // how infectious the node is on this day of its infection
+            let infectiousness = seasonal * network_properties.relative_infectiousness(i) * match (&profile, network_properties.days_infectious(i, day)) {
+                (Some(values), Some(days)) => values.get(days).copied().unwrap_or(0.0),
+                _ => 1.0
+            };
+            // find connections to infected individuals
+            for &(j, edge) in neighbours[i].iter() {
+                if let State::Susceptible = network_properties.nodal_states[j] {
+                    let p = network_properties.transmission_probability(network_structure, i, j, edge) * infectiousness;
+                    let infected = rng.gen::<f64>() < p;
+                    // the first infector of the day is the one recorded
+                    if infected && matches!(next_states[j], State::Susceptible) {
+                        next_states[j] = State::Exposed(latent_period.sample(rng));
+                        network_properties.secondary_cases[i] += 1;
+                        network_properties.infected_by[j] = Some(i);
+                        network_properties.transmissions.push((day, InfectionSource::Node(i), j));
+                        network_properties.infection_day[j] = Some(day);
+                    }
                 }
             }
         }
     }
+    for i in infectious_onsets.into_iter() {
+        network_properties.infectious_day[i] = Some(day + 1);
+    }
+    // infections from outside, of nodes that stay susceptible through the day
+    if let Some(importation) = network_properties.importation.clone() {
+        let mut imported: Vec<usize> = importation.draw(network_structure, network_properties, day, rng)
+            .into_iter()
+            .filter(|&j| matches!(network_properties.nodal_states[j], State::Susceptible) && matches!(next_states[j], State::Susceptible))
+            .collect();
+        imported.sort_unstable();
+        imported.dedup();
+        for &j in imported.iter() {
+            next_states[j] = match (&importation.state, &presymptomatic_period) {
+                (SeedState::Exposed, _) => State::Exposed(latent_period.sample(rng)),
+                (SeedState::Infectious, Some(period)) => State::Presymptomatic(period.sample(rng)),
+                (SeedState::Infectious, None) => State::Infected(infectious_period.sample(rng))
+            };
+            network_properties.transmissions.push((day, InfectionSource::Imported, j));
+            // no infector in the network, rather than whoever infected the node last time
+            network_properties.infected_by[j] = None;
+            network_properties.infection_day[j] = Some(day);
+            if let SeedState::Infectious = importation.state {
+                network_properties.infectious_day[j] = Some(day + 1);
+            }
+        }
+    }
     network_properties.nodal_states = next_states;
     network_properties.results.push(network_properties.count_states());
 }
@@ -2,6 +2,25 @@ use crate::random_graphs::*;
 // use crate::tau_leap::*;
 use crate::run_model::*;
 use crate::write_to_file::*;
+use crate::edge_based_model::*;
+use crate::mean_field::*;
+use crate::useful_functions::calibrate_activity_correlation;
+use crate::fitting::fit_survey_to_json;
+use crate::ode_solver::OdeSolver;
+use crate::dynamic_network::*;
+use crate::temporal_network::*;
+use crate::timeline::*;
+use crate::interventions::*;
+use crate::vaccination::*;
+use crate::targeting::*;
+use crate::contact_tracing::*;
+use crate::natural_history::*;
+use crate::severity::*;
+use crate::periods::*;
+use crate::infectiousness::*;
+use crate::seasonality::*;
+use crate::seeding::*;
+use crate::importation::*;
 
 pub fn test_run_model(n: usize) {
     // define network with initial infection
@@ -18,19 +37,417 @@ pub fn test_run_model(n: usize) {
     let initially_infected = 0.005;
 
     let start = std::time::Instant::now();
-    let output = run_model(&network_structure, &mut network_properties, maxtime, initially_infected);
+    let output = run_model(&network_structure, &mut network_properties, maxtime, initially_infected).unwrap();
     let elapsed = start.elapsed();
     println!("{} seconds", elapsed.as_secs());
     outbreak_results_csv(output, network_properties.result_type,"model_output_files/secondary_cases_config2.csv");
     // outbreak_results_csv(output, network_properties.result_type,"../../csv/test.csv");
 }
 
+pub fn test_edge_based_model(n: usize) {
+    // deterministic counterpart of test_run_model, to overlay on the stochastic curves
+    let partitions = vec![n/9, 2*n/9, 3*n/9, 4*n/9, 5*n/9, 6*n/9, 7*n/9, 8*n/9, n];
+    let model = EdgeBasedModel::from_file(&partitions, "model_input_files/fitting_parameters2.json", 
+        &[0.02, 3.0, 7.0, 1000.0], 0.005);
+    println!("R0 = {}", model.r0());
+    let output = run_edge_based_model(&model, 50.0, OdeSolver::RK45(1e-8));
+    outbreak_results_csv(output, ResultType::SEIR, "model_output_files/seir_edge_based2.csv");
+}
+
+pub fn test_sbm_with_mean_field(n: usize) {
+    // SBM scenario paired with the well-mixed model built from the same rates and partitions
+    let partitions = vec![n/9, 2*n/9, 3*n/9, 4*n/9, 5*n/9, 6*n/9, 7*n/9, 8*n/9, n];
+    let rates_mat = read_rates_mat("model_input_files/rates_matrix2.csv");
+    let network_structure = NetworkStructure::new_sbm(n, partitions.clone(), rates_mat.clone());
+    let mut network_properties = NetworkProperties::new(&network_structure);
+    network_properties.params(vec![0.02, 3.0, 7.0, 1000.0]);
+    let (output, baseline) = run_with_mean_field(&network_structure, &mut network_properties, &partitions, &rates_mat, 50.0, 0.005).unwrap();
+    outbreak_results_csv(output, ResultType::SEIR, "model_output_files/seir_SBM2.csv");
+    outbreak_results_csv(baseline, ResultType::SEIR, "model_output_files/seir_mean_field2.csv");
+}
+
+pub fn run_with_mean_field(network_structure: &NetworkStructure, network_properties: &mut NetworkProperties, partitions: &[usize], 
+    rates_mat: &[Vec<f64>], maxtime: f64, initially_infected: f64) -> Result<(Output, Output), SeedingError> {
+    
+    // the baseline always reports the SEIR trajectory, so the network run is made to match
+    network_properties.result_type = ResultType::SEIR;
+    let model = MeanFieldModel::from_network_properties(partitions, rates_mat, network_properties, initially_infected);
+    println!("mean-field R0 = {}", model.r0());
+    let output = run_model(network_structure, network_properties, maxtime, initially_infected)?;
+    let baseline = run_mean_field_model(&model, maxtime, OdeSolver::RK45(1e-8));
+    Ok((output, baseline))
+}
+
+pub fn test_fit_survey(n: usize) {
+    // fit the contact distributions from raw survey records and build the network from them
+    // the example files are a small made up survey in the same format as a real one
+    let age_limits = [0.0, 5.0, 12.0, 18.0, 30.0, 40.0, 50.0, 60.0, 70.0];
+    let reports = fit_survey_to_json("model_input_files/contact_survey.csv", 
+        Some("model_input_files/survey_participants.csv"), &age_limits, 
+        "model_input_files/fitting_parameters_survey.json");
+    for report in reports.iter() {
+        println!("{:?}", report);
+    }
+    let network_structure = NetworkStructure::new_molloy_reed(n, vec![n/9, 2*n/9, 3*n/9, 4*n/9, 5*n/9, 6*n/9, 7*n/9, 8*n/9, n], 
+        "model_input_files/fitting_parameters_survey.json");
+    network_structure_json(&network_structure, "model_output_files/network_config_survey.json")
+}
+
+pub fn test_run_model_multilayer(n: usize) {
+    // home and community layers from separate generators, with household contacts
+    // transmitting more readily than community ones
+    let partitions = vec![n/9, 2*n/9, 3*n/9, 4*n/9, 5*n/9, 6*n/9, 7*n/9, 8*n/9, n];
+    let community = NetworkStructure::new_molloy_reed(n, partitions.clone(), "model_input_files/fitting_parameters2.json");
+    let home = NetworkStructure::new_households(n, partitions, &read_household_json("model_input_files/households.json"));
+    let network_structure = NetworkStructure::new_multilayer(vec![("community", community), ("home", home)]);
+    let mut network_properties = NetworkProperties::new(&network_structure);
+    network_properties.params(vec![0.02, 3.0, 7.0, 1000.0]);
+    network_properties.set_layer_multiplier(&network_structure, "home", 3.0);
+    let output = run_model(&network_structure, &mut network_properties, 50.0, 0.005).unwrap();
+    outbreak_results_csv(output, network_properties.result_type, "model_output_files/seir_multilayer2.csv");
+}
+
+pub fn test_run_model_weighted(n: usize) {
+    // household contacts are daily, community contacts a mix of daily, weekly and one-off,
+    // with transmission on each day a contact happens
+    let partitions = vec![n/9, 2*n/9, 3*n/9, 4*n/9, 5*n/9, 6*n/9, 7*n/9, 8*n/9, n];
+    let community = NetworkStructure::new_molloy_reed(n, partitions.clone(), "model_input_files/fitting_parameters2.json");
+    let home = NetworkStructure::new_households(n, partitions, &read_household_json("model_input_files/households.json"));
+    let mut network_structure = NetworkStructure::new_multilayer(vec![("community", community), ("home", home)]);
+    network_structure.assign_layer_weights("community", &read_weights_json("model_input_files/contact_weights.json"));
+    let mut network_properties = NetworkProperties::new(&network_structure);
+    network_properties.params(vec![0.05, 3.0, 7.0, 1000.0]);
+    network_properties.transmission_mode = TransmissionMode::Frequency;
+    let output = run_model(&network_structure, &mut network_properties, 100.0, 0.005).unwrap();
+    outbreak_results_csv(output, network_properties.result_type, "model_output_files/seir_weighted2.csv");
+}
+
+pub fn test_run_model_dynamic(n: usize, persistent_fraction: f64) {
+    // a share of everyone's contacts repeat every day, the rest are redrawn from the same
+    // distributions, compared with the static network
+    let partitions = vec![n/9, 2*n/9, 3*n/9, 4*n/9, 5*n/9, 6*n/9, 7*n/9, 8*n/9, n];
+    let dist_params = read_params_json("model_input_files/fitting_parameters2.json");
+    let mut dynamic_network = DynamicNetwork::with_persistent_fraction(n, partitions.clone(), TransientContacts::Configuration(dist_params), persistent_fraction, &mut rand::thread_rng()).unwrap();
+    let mut network_properties = NetworkProperties::new(&dynamic_network.today);
+    network_properties.params(vec![0.02, 3.0, 7.0, 1000.0]);
+    let output = run_model_dynamic(&mut dynamic_network, &mut network_properties, 100.0, 0.005).unwrap();
+    outbreak_results_csv(output, network_properties.result_type, "model_output_files/seir_dynamic2.csv");
+
+    let network_structure = NetworkStructure::new_molloy_reed(n, partitions, "model_input_files/fitting_parameters2.json");
+    let mut network_properties = NetworkProperties::new(&network_structure);
+    network_properties.params(vec![0.02, 3.0, 7.0, 1000.0]);
+    let output = run_model(&network_structure, &mut network_properties, 100.0, 0.005).unwrap();
+    outbreak_results_csv(output, network_properties.result_type, "model_output_files/seir_static2.csv");
+}
+
+pub fn test_run_model_temporal(file_path: &str, time_step: f64) {
+    // outbreak on time stamped contacts against the same contacts aggregated into one network,
+    // with parameters[0] the probability of transmission per unit of contact duration
+    let mut temporal_network = TemporalNetwork::from_file(file_path, time_step);
+    temporal_network.repeat = true;
+    let network_structure = temporal_network.to_static();
+
+    let mut network_properties = NetworkProperties::new(&network_structure);
+    network_properties.params(vec![0.01, 2.0, 5.0, 1000.0]);
+    network_properties.transmission_mode = TransmissionMode::Duration;
+    let output = run_model_temporal(&temporal_network, &mut network_properties, 100.0, 0.01).unwrap();
+    outbreak_results_csv(output, network_properties.result_type, "model_output_files/seir_temporal.csv");
+
+    let mut network_properties = NetworkProperties::new(&network_structure);
+    network_properties.params(vec![0.01, 2.0, 5.0, 1000.0]);
+    network_properties.transmission_mode = TransmissionMode::Duration;
+    let output = run_model(&network_structure, &mut network_properties, 100.0, 0.01).unwrap();
+    outbreak_results_csv(output, network_properties.result_type, "model_output_files/seir_temporal_aggregated.csv");
+}
+
+pub fn test_run_model_lockdown(n: usize, start: usize, end: usize) {
+    // open period contacts, thinned to the lockdown period between start and end
+    let partitions = vec![n/9, 2*n/9, 3*n/9, 4*n/9, 5*n/9, 6*n/9, 7*n/9, 8*n/9, n];
+    let network_structure = NetworkStructure::new_molloy_reed(n, partitions.clone(), "model_input_files/fitting_parameters2.json");
+    let mut timeline = NetworkTimeline::new(network_structure, partitions);
+    timeline.rewire_from_file(start, "model_input_files/fitting_parameters1.json");
+    timeline.rewire_from_file(end, "model_input_files/fitting_parameters2.json");
+    let mut network_properties = NetworkProperties::new(&timeline.network);
+    network_properties.params(vec![0.02, 3.0, 7.0, 1000.0]);
+    let output = run_model_timeline(&mut timeline, &mut network_properties, 100.0, 0.005).unwrap();
+    outbreak_results_csv(output, network_properties.result_type, "model_output_files/seir_lockdown.csv");
+}
+
+pub fn test_run_model_interventions(n: usize) {
+    // schools closed for fifty days, shielding of the oldest brackets once prevalence reaches
+    // one percent, and contacts halved while incidence is high
+    let partitions = vec![n/9, 2*n/9, 3*n/9, 4*n/9, 5*n/9, 6*n/9, 7*n/9, 8*n/9, n];
+    let network_structure = NetworkStructure::new_molloy_reed(n, partitions, "model_input_files/fitting_parameters2.json");
+    let mut network_properties = NetworkProperties::new(&network_structure);
+    network_properties.params(vec![0.02, 3.0, 7.0, 1000.0]);
+    let mut interventions: Vec<Box<dyn Intervention>> = vec![
+        Box::new(SchoolClosure::new(None, vec![0, 1], Schedule::between(10, 60))),
+        Box::new(Shielding::new(vec![7, 8], 0.8, Schedule::new(Trigger::PrevalenceAbove(0.01), None))),
+        Box::new(ContactReduction::new(0.5, None, Schedule::new(Trigger::IncidenceAbove(0.005), Some(Trigger::IncidenceBelow(0.001)))))
+    ];
+    let output = run_model_with_interventions(&network_structure, &mut network_properties, 100.0, 0.005, &mut interventions).unwrap();
+    for (day, event) in output.events.iter() {
+        println!("day {day}: {event}");
+    }
+    outbreak_results_csv(output, network_properties.result_type, "model_output_files/seir_interventions2.csv");
+}
+
+pub fn test_run_model_vaccination(n: usize, daily_capacity: usize) {
+    // two dose leaky vaccine given oldest first from day ten, waning with a six month half life
+    let partitions = vec![n/9, 2*n/9, 3*n/9, 4*n/9, 5*n/9, 6*n/9, 7*n/9, 8*n/9, n];
+    let network_structure = NetworkStructure::new_molloy_reed(n, partitions, "model_input_files/fitting_parameters2.json");
+    let mut network_properties = NetworkProperties::new(&network_structure);
+    network_properties.params(vec![0.02, 3.0, 7.0, 1000.0]);
+    let vaccine = Vaccine {
+        efficacy_infection: vec![0.6, 0.9],
+        efficacy_transmission: vec![0.3, 0.5],
+        action: VaccineAction::Leaky,
+        dose_interval: 21,
+        waning_half_life: Some(180.0)
+    };
+    let campaign = VaccinationCampaign::new(vaccine, Prioritisation::AgeOrder(vec![8, 7, 6, 5, 4, 3, 2, 1, 0]), daily_capacity, 10);
+    let mut interventions: Vec<Box<dyn Intervention>> = vec![Box::new(campaign)];
+    let output = run_model_with_interventions(&network_structure, &mut network_properties, 100.0, 0.005, &mut interventions).unwrap();
+    println!("{} doses delivered", output.doses_delivered.iter().sum::<usize>());
+    outbreak_results_csv(output, network_properties.result_type, "model_output_files/seir_vaccination2.csv");
+}
+
+pub fn test_compare_targeting(n: usize, iterations: usize) {
+    // attack rate against coverage for every network based ranking
+    let partitions = vec![n/9, 2*n/9, 3*n/9, 4*n/9, 5*n/9, 6*n/9, 7*n/9, 8*n/9, n];
+    let network_structure = NetworkStructure::new_molloy_reed(n, partitions, "model_input_files/fitting_parameters2.json");
+    let strategies = vec![
+        TargetingStrategy::Degree,
+        TargetingStrategy::Acquaintance,
+        TargetingStrategy::Betweenness { samples: 100 },
+        TargetingStrategy::Eigenvector { iterations: 100 },
+        TargetingStrategy::KCore,
+        TargetingStrategy::DegreeWithinAge
+    ];
+    let coverages: Vec<f64> = (0..=10).map(|x| (x as f64) / 20.0).collect();
+    let results = compare_targeting(&network_structure, &[0.02, 3.0, 7.0, 1000.0], &strategies, &coverages, 1000.0, 0.005, iterations).unwrap();
+    if let Err(err) = results_json(&results, "model_output_files/targeting2.json") {
+        eprintln!("Error: {}", err);
+    }
+}
+
+pub fn test_run_model_tracing(n: usize, seed: u64) {
+    // half of cases detected a day after onset, with forward and backward tracing of their contacts
+    let partitions = vec![n/9, 2*n/9, 3*n/9, 4*n/9, 5*n/9, 6*n/9, 7*n/9, 8*n/9, n];
+    let network_structure = NetworkStructure::new_molloy_reed(n, partitions, "model_input_files/fitting_parameters2.json");
+    let mut network_properties = NetworkProperties::new(&network_structure);
+    network_properties.params(vec![0.02, 3.0, 7.0, 1000.0]);
+    network_properties.seed = Some(seed);
+    let mut tracing = ContactTracing::new(0.5, 1, 0.6, 1, 14, 0.8);
+    tracing.backward = true;
+    let output = run_model_with_tracing(&network_structure, &mut network_properties, 100.0, 0.005, tracing).unwrap();
+    println!("{} infections prevented, at most {} in quarantine", output.infections_prevented, output.quarantined.iter().max().unwrap_or(&0));
+    outbreak_results_csv(output, network_properties.result_type, "model_output_files/seir_tracing2.csv");
+}
+
+pub fn test_run_model_natural_history(n: usize) {
+    // two presymptomatic days, asymptomatic infection more likely in the young, and tracing that
+    // only detects symptomatic cases
+    let partitions = vec![n/9, 2*n/9, 3*n/9, 4*n/9, 5*n/9, 6*n/9, 7*n/9, 8*n/9, n];
+    let network_structure = NetworkStructure::new_molloy_reed(n, partitions, "model_input_files/fitting_parameters2.json");
+    let mut network_properties = NetworkProperties::new(&network_structure);
+    network_properties.params(vec![0.02, 3.0, 5.0, 1000.0]);
+    network_properties.natural_history = Some(NaturalHistory {
+        presymptomatic_period: 2.0,
+        asymptomatic_probability: vec![0.7, 0.6, 0.5, 0.4, 0.35, 0.3, 0.25, 0.2, 0.2],
+        presymptomatic_infectiousness: 1.0,
+        symptomatic_infectiousness: 1.0,
+        asymptomatic_infectiousness: 0.5
+    });
+    let tracing = ContactTracing::new(0.7, 1, 0.6, 1, 14, 0.8);
+    let output = run_model_with_tracing(&network_structure, &mut network_properties, 100.0, 0.005, tracing).unwrap();
+    println!("{} infections prevented", output.infections_prevented);
+    outbreak_results_csv(output, network_properties.result_type, "model_output_files/seir_natural_history2.csv");
+}
+
+pub fn test_run_model_severity(n: usize, bed_capacity: usize, iterations: usize) {
+    // hospital burden by age over an ensemble, with worse outcomes once the beds are full
+    let partitions = vec![n/9, 2*n/9, 3*n/9, 4*n/9, 5*n/9, 6*n/9, 7*n/9, 8*n/9, n];
+    let network_structure = NetworkStructure::new_molloy_reed(n, partitions, "model_input_files/fitting_parameters2.json");
+    let mut network_properties = NetworkProperties::new(&network_structure);
+    network_properties.params(vec![0.02, 3.0, 7.0, 1000.0]);
+    let delays = SeverityDelays { onset_to_admission: 5.0, onset_to_icu: 7.0, onset_to_death: 14.0, length_of_stay: 8.0 };
+    let severity = Severity::new(SeverityProbabilities::from_file("model_input_files/severity.csv"), delays)
+        .with_capacity(bed_capacity, 1.5);
+    let (_, summary) = run_ensemble_severity(&network_structure, &network_properties, 200.0, 0.005, &severity, iterations).unwrap();
+    results_json(&summary, "model_output_files/severity_summary2.json").expect("Failed to write severity summary");
+}
+
+pub fn test_run_model_periods(n: usize) {
+    // Erlang latent period, infectious period from file and waning immunity, with initial cases
+    // part way through their infectious period
+    let partitions = vec![n/9, 2*n/9, 3*n/9, 4*n/9, 5*n/9, 6*n/9, 7*n/9, 8*n/9, n];
+    let network_structure = NetworkStructure::new_molloy_reed(n, partitions, "model_input_files/fitting_parameters2.json");
+    let mut network_properties = NetworkProperties::new(&network_structure);
+    network_properties.params(vec![0.02, 3.0, 7.0, 1000.0]);
+    network_properties.latent_period = Some(PeriodDistribution::Gamma { shape: 3.0, mean: 3.0 });
+    network_properties.infectious_period = Some(PeriodDistribution::from_file("model_input_files/infectious_period.csv"));
+    network_properties.immune_period = Some(PeriodDistribution::LogNormal { mu: 4.0, sigma: 0.5 });
+    network_properties.seed_periods = SeedPeriods::Residual;
+    let output = run_model(&network_structure, &mut network_properties, 300.0, 0.005).unwrap();
+    outbreak_results_csv(output, network_properties.result_type, "model_output_files/seir_periods2.csv");
+}
+
+pub fn test_run_model_infectiousness_profile(n: usize) {
+    // gamma shaped infectiousness peaking early in the infectious period, with the generation
+    // intervals written out to compare with estimates
+    let partitions = vec![n/9, 2*n/9, 3*n/9, 4*n/9, 5*n/9, 6*n/9, 7*n/9, 8*n/9, n];
+    let network_structure = NetworkStructure::new_molloy_reed(n, partitions, "model_input_files/fitting_parameters2.json");
+    let mut network_properties = NetworkProperties::new(&network_structure);
+    network_properties.params(vec![0.02, 3.0, 7.0, 1000.0]);
+    network_properties.infectiousness_profile = Some(InfectiousnessProfile::Parametric {
+        distribution: PeriodDistribution::Gamma { shape: 2.0, mean: 3.0 },
+        total: 8.0
+    });
+    let output = run_model(&network_structure, &mut network_properties, 200.0, 0.005).unwrap();
+    results_json(&generation_intervals(&output), "model_output_files/generation_intervals2.json").expect("Failed to write generation intervals");
+    outbreak_results_csv(output, network_properties.result_type, "model_output_files/seir_profile2.csv");
+}
+
+pub fn test_run_model_age_multipliers(n: usize, shape: f64) {
+    // children less susceptible and the old more so, with gamma distributed individual variation,
+    // alongside the analytical R0 with the same multipliers
+    let partitions = vec![n/9, 2*n/9, 3*n/9, 4*n/9, 5*n/9, 6*n/9, 7*n/9, 8*n/9, n];
+    let network_structure = NetworkStructure::new_molloy_reed(n, partitions.clone(), "model_input_files/fitting_parameters2.json");
+    let mut network_properties = NetworkProperties::new(&network_structure);
+    network_properties.params(vec![0.02, 3.0, 7.0, 1000.0]);
+    network_properties.age_susceptibility = vec![0.5, 0.5, 0.7, 1.0, 1.0, 1.0, 1.0, 1.2, 1.3];
+    network_properties.age_infectiousness = vec![0.8, 0.8, 0.9, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0];
+    network_properties.draw_heterogeneity(Some(shape), Some(shape));
+    let (susceptibility, infectiousness) = network_properties.block_multipliers(&partitions);
+    let model = EdgeBasedModel::from_file(&partitions, "model_input_files/fitting_parameters2.json", &network_properties.parameters, 0.005)
+        .with_multipliers(susceptibility, infectiousness);
+    println!("R0 = {}", model.r0());
+    let output = run_model(&network_structure, &mut network_properties, 200.0, 0.005).unwrap();
+    outbreak_results_csv(output, network_properties.result_type, "model_output_files/seir_age_multipliers2.csv");
+}
+
+pub fn test_run_model_seasonal(n: usize, years: usize, amplitude: f64) {
+    // SEIRS over several years with three months of immunity and transmission peaking in winter
+    let partitions = vec![n/9, 2*n/9, 3*n/9, 4*n/9, 5*n/9, 6*n/9, 7*n/9, 8*n/9, n];
+    let network_structure = NetworkStructure::new_molloy_reed(n, partitions, "model_input_files/fitting_parameters2.json");
+    let mut network_properties = NetworkProperties::new(&network_structure);
+    network_properties.params(vec![0.01, 3.0, 7.0, 90.0]);
+    network_properties.transmission_schedule = Some(TransmissionSchedule::Sinusoid { amplitude, period: 365.0, peak_day: 0.0 });
+    let output = run_model(&network_structure, &mut network_properties, (365*years) as f64, 0.005).unwrap();
+    outbreak_results_csv(output, network_properties.result_type, "model_output_files/seir_seasonal2.csv");
+}
+
+pub fn test_run_model_seeding(n: usize, count: usize) {
+    // existing immunity by age, then exposed cases among the most connected tenth of nodes
+    let partitions = vec![n/9, 2*n/9, 3*n/9, 4*n/9, 5*n/9, 6*n/9, 7*n/9, 8*n/9, n];
+    let network_structure = NetworkStructure::new_molloy_reed(n, partitions, "model_input_files/fitting_parameters2.json");
+    let mut network_properties = NetworkProperties::new(&network_structure);
+    network_properties.params(vec![0.02, 3.0, 7.0, 1000.0]);
+    let seeded = network_properties.immunity_from_file(&network_structure, "model_input_files/seroprevalence.csv")
+        .and_then(|_| network_properties.seed_infection(&network_structure, &Seeding::DegreeQuantile { lower: 0.9, upper: 1.0, count }, SeedState::Exposed));
+    if let Err(err) = seeded {
+        println!("{}", err);
+        return
+    }
+    let output = run_model(&network_structure, &mut network_properties, 200.0, 0.0).unwrap();
+    outbreak_results_csv(output, network_properties.result_type, "model_output_files/seir_seeding2.csv");
+}
+
+pub fn test_run_model_importation(n: usize, years: usize, daily_imports: f64) {
+    // seasonal SEIRS kept going by imported cases among working age adults, with no initial cases
+    let partitions = vec![n/9, 2*n/9, 3*n/9, 4*n/9, 5*n/9, 6*n/9, 7*n/9, 8*n/9, n];
+    let network_structure = NetworkStructure::new_molloy_reed(n, partitions, "model_input_files/fitting_parameters2.json");
+    let mut network_properties = NetworkProperties::new(&network_structure);
+    network_properties.params(vec![0.012, 3.0, 7.0, 120.0]);
+    network_properties.transmission_schedule = Some(TransmissionSchedule::Sinusoid { amplitude: 0.3, period: 365.0, peak_day: 0.0 });
+    let mut importation = Importation::new(ImportRate::Constant(daily_imports));
+    importation.age_distribution = Some(vec![0.0, 0.0, 0.5, 1.0, 1.0, 1.0, 0.5, 0.2, 0.1]);
+    network_properties.importation = Some(importation);
+    let output = run_model(&network_structure, &mut network_properties, (365*years) as f64, 0.0).unwrap();
+    let imported = output.transmissions.iter().filter(|(_, source, _)| *source == InfectionSource::Imported).count();
+    println!("{} of {} infections imported", imported, output.transmissions.len());
+    outbreak_results_csv(output, network_properties.result_type, "model_output_files/seir_importation2.csv");
+}
+
 pub fn test_create_network_config(n: usize) {
     let network_structure = NetworkStructure::new_molloy_reed(n, vec![n/9, 2*n/9, 3*n/9, 4*n/9, 5*n/9, 6*n/9, 7*n/9, 8*n/9, n], 
         "model_input_files/fitting_parameters2.json");
     network_structure_json(&network_structure, "model_output_files/network_config2.json")
 }
 
+pub fn test_create_network_config_correlated(n: usize, target_variance: f64) {
+    // calibrate the shared activity of each bracket to the same total contact variance
+    let file_path = "model_input_files/fitting_parameters2.json";
+    let dist_params = read_params_json(file_path);
+    let activity_correlation: Vec<f64> = (0..dist_params.blocks())
+        .map(|i| calibrate_activity_correlation(&dist_params, i, target_variance))
+        .collect();
+    let network_structure = NetworkStructure::new_molloy_reed_correlated(n, vec![n/9, 2*n/9, 3*n/9, 4*n/9, 5*n/9, 6*n/9, 7*n/9, 8*n/9, n], 
+        file_path, &activity_correlation).unwrap();
+    network_structure_json(&network_structure, "model_output_files/network_config_correlated2.json")
+}
+
+pub fn test_create_network_chung_lu(n: usize) {
+    // expected degrees and mixing taken from the fitted contact distributions, so the result
+    // is comparable with new_molloy_reed without the bias from erasing repeated stubs
+    let partitions = vec![n/9, 2*n/9, 3*n/9, 4*n/9, 5*n/9, 6*n/9, 7*n/9, 8*n/9, n];
+    let dist_params = read_params_json("model_input_files/fitting_parameters2.json");
+    let mut rng = rand::thread_rng();
+    let blocks = dist_params.blocks();
+    let mixing_mat: Vec<Vec<f64>> = (0..blocks)
+        .map(|i| (0..blocks).map(|j| dist_params.distribution(i, j).mean()).collect())
+        .collect();
+    let samplers = dist_params.samplers().unwrap();
+    let mut last_idx = 0;
+    let expected_degrees: Vec<f64> = partitions
+        .iter()
+        .enumerate()
+        .flat_map(|(i, x)| {
+            let degrees: Vec<f64> = (last_idx..*x)
+                .map(|_| samplers[i].iter().map(|d| d.sample(&mut rng) as f64).sum())
+                .collect();
+            last_idx = *x;
+            degrees
+        })
+        .collect();
+    let network_structure = NetworkStructure::new_chung_lu(n, partitions, expected_degrees, mixing_mat);
+    network_structure_json(&network_structure, "model_output_files/network_chung_lu2.json")
+}
+
+pub fn test_create_network_households(n: usize) {
+    // household layer on top of the configuration model community contacts
+    let network_structure = NetworkStructure::new_molloy_reed(n, vec![n/9, 2*n/9, 3*n/9, 4*n/9, 5*n/9, 6*n/9, 7*n/9, 8*n/9, n], 
+        "model_input_files/fitting_parameters2.json")
+        .with_households("model_input_files/households.json");
+    network_structure_json(&network_structure, "model_output_files/network_config_households2.json")
+}
+
+pub fn test_create_network_sbm_weighted(n: usize) {
+    // edges between each pair of brackets against the number expected from the rates
+    let network_structure = comix_sbm_weighted(n);
+    let brackets = network_structure.age_brackets.iter().max().map_or(0, |x| x + 1);
+    let mut sizes: Vec<f64> = vec![0.0; brackets];
+    for a in network_structure.age_brackets.iter() {
+        sizes[*a] += 1.0;
+    }
+    let mut edges: Vec<Vec<f64>> = vec![vec![0.0; brackets]; brackets];
+    for (i, j, _) in network_structure.adjacency_matrix.triplet_iter() {
+        if i > j {
+            let (a, b) = (network_structure.age_brackets[i], network_structure.age_brackets[j]);
+            edges[a.max(b)][a.min(b)] += 1.0;
+        }
+    }
+    let rates_mat = comix_rates();
+    for a in 0..brackets {
+        for b in 0..=a {
+            let pairs = if a == b { sizes[a]*(sizes[a] - 1.0)/2.0 } else { sizes[a]*sizes[b] };
+            println!("brackets {} {}, edges {}, expected {:.1}", a, b, edges[a][b], pairs*rates_mat[a][b]/sizes[a]);
+        }
+    }
+    network_structure_json(&network_structure, "model_output_files/network_sbm_weighted.json")
+}
+
 pub fn test_create_network_SBM(n: usize) {
     let rates_mat = read_rates_mat("model_input_files/rates_matrix2.csv");
     let network_structure = NetworkStructure::new_sbm(n, vec![n/9, 2*n/9, 3*n/9, 4*n/9, 5*n/9, 6*n/9, 7*n/9, 8*n/9, n], 
@@ -101,7 +518,7 @@ pub fn comix_sbm(n: usize) -> NetworkStructure {
 pub fn comix_sbm_weighted(n: usize) -> NetworkStructure {
 
     // age brackets, 0-17 / 18-39 / 40-65 / 65+
-    // proportions,  20.7 / 28.5  / 32.2  / 19.6nrows
+    // proportions,  20.7 / 28.5  / 32.2  / 19.6
     // https://www.ethnicity-facts-figures.service.gov.uk/uk-population-by-ethnicity/demographics/age-groups/latest
     let partitions: Vec<usize> = vec![
         (20.7*(n as f64)/100.0) as usize, 
@@ -110,13 +527,18 @@ pub fn comix_sbm_weighted(n: usize) -> NetworkStructure {
         n
     ];
 
-    let rates_mat: Vec<Vec<f64>> = vec![
+    let rates_mat = comix_rates();
+    // negative binomial contact heterogeneity, mu = 4.79, k = 0.54
+    NetworkStructure::new_sbm_weighted(n, partitions, rates_mat, WeightDistribution::NegativeBinomial { mu: 4.79, k: 0.54 })
+}
+
+fn comix_rates() -> Vec<Vec<f64>> {
+    vec![
         vec![5.3, 1.1, 2.1, 0.31],
         vec![1.1, 1.4, 2.2, 0.77],
         vec![2.1, 2.2, 2.4, 1.1],
         vec![0.31, 0.77, 1.1, 1.4]
-    ];
-    NetworkStructure::new_sbm_weighted(n, partitions, rates_mat)
+    ]
 }
 
 pub fn network_structure_json(network_structure: &NetworkStructure, file_path: &str) {
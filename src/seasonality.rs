@@ -0,0 +1,50 @@
+use crate::random_graphs::NetworkProperties;
+use crate::write_to_file::read_transmission_csv;
+use std::f64::consts::PI;
+
+// Transmissibility that changes over the run, e.g. with the seasons. The multiplier for the
+// day scales every chance of transmission in step_model.
+
+#[derive(Clone, Debug)]
+pub enum TransmissionSchedule {
+    // 1 + amplitude*cos(2*pi*(day - peak_day)/period), e.g. a period of 365 for yearly cycles
+    Sinusoid { amplitude: f64, period: f64, peak_day: f64 },
+    // multipliers from each start day until the next, sorted by day, with 1 before the first
+    Piecewise(Vec<(usize, f64)>),
+    // a multiplier for each day, after the end either starting again or keeping the last value
+    Series { values: Vec<f64>, repeat: bool }
+}
+
+impl TransmissionSchedule {
+
+    pub fn from_file(file_path: &str, repeat: bool) -> TransmissionSchedule {
+        TransmissionSchedule::Series { values: read_transmission_csv(file_path), repeat }
+    }
+
+    pub fn multiplier(&self, day: usize) -> f64 {
+        match self {
+            TransmissionSchedule::Sinusoid { amplitude, period, peak_day } => {
+                (1.0 + amplitude * (2.0 * PI * (day as f64 - peak_day) / period).cos()).max(0.0)
+            },
+            TransmissionSchedule::Piecewise(steps) => {
+                steps.iter()
+                    .take_while(|(start, _)| *start <= day)
+                    .last()
+                    .map_or(1.0, |(_, x)| *x)
+            },
+            TransmissionSchedule::Series { values, repeat } => {
+                match (values.is_empty(), repeat) {
+                    (true, _) => 1.0,
+                    (false, true) => values[day % values.len()],
+                    (false, false) => values[day.min(values.len() - 1)]
+                }
+            }
+        }
+    }
+}
+
+impl NetworkProperties {
+    pub fn transmission_multiplier(&self, day: usize) -> f64 {
+        self.transmission_schedule.as_ref().map_or(1.0, |x| x.multiplier(day))
+    }
+}
@@ -0,0 +1,222 @@
+use crate::periods::{sample_pmf, PeriodDistribution, SeedPeriods};
+use crate::random_graphs::*;
+use crate::write_to_file::seroprevalence_csv;
+use rand::prelude::*;
+use std::error::Error;
+use std::fmt;
+
+// Initial conditions beyond the random proportion of initialize_infection. Seed before the run
+// and pass an initially_infected of zero to the runners, which then add no further cases.
+
+#[derive(Clone, Debug)]
+pub enum Seeding {
+    // a share of the population, or an exact number of cases, chosen at random
+    Proportion(f64),
+    Count(usize),
+    // number of cases in each age bracket
+    AgeCounts(Vec<usize>),
+    // cases among the nodes between two quantiles of degree, e.g. 0.9 and 1.0 for the top tenth
+    DegreeQuantile { lower: f64, upper: f64, count: usize },
+    HighestDegree(usize),
+    Nodes(Vec<usize>),
+    // a random node and its neighbours, then another, until there are count cases
+    Clustered(usize)
+}
+
+#[derive(Clone, Debug)]
+pub enum SeedState {
+    // infectious, or presymptomatic with the extended natural history
+    Infectious,
+    Exposed
+}
+
+#[derive(Debug)]
+pub enum SeedingError {
+    // a proportion or quantile outside 0 to 1
+    InvalidProportion(f64),
+    // more cases than there are susceptible nodes to choose from
+    TooMany { requested: usize, available: usize },
+    // a lower quantile above the upper one
+    InvalidQuantiles { lower: f64, upper: f64 },
+    UnknownNode(usize),
+    UnknownAgeBracket(usize),
+    // an input file that could not be read
+    Io(Box<dyn Error>)
+}
+
+impl fmt::Display for SeedingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SeedingError::InvalidProportion(x) => write!(f, "proportion {} must be between 0 and 1", x),
+            SeedingError::TooMany { requested, available } => write!(f, "{} initial cases requested but only {} nodes are susceptible", requested, available),
+            SeedingError::UnknownNode(x) => write!(f, "node {} is not in the network", x),
+            SeedingError::InvalidQuantiles { lower, upper } => write!(f, "lower quantile {} is above the upper quantile {}", lower, upper),
+            SeedingError::UnknownAgeBracket(x) => write!(f, "age bracket {} is not in the network", x),
+            SeedingError::Io(err) => write!(f, "{}", err)
+        }
+    }
+}
+
+impl Error for SeedingError {}
+
+fn check_proportion(x: f64) -> Result<f64, SeedingError> {
+    if (0.0..=1.0).contains(&x) { Ok(x) } else { Err(SeedingError::InvalidProportion(x)) }
+}
+
+fn choose<R: Rng>(mut candidates: Vec<usize>, count: usize, rng: &mut R) -> Result<Vec<usize>, SeedingError> {
+    if count > candidates.len() {
+        return Err(SeedingError::TooMany { requested: count, available: candidates.len() })
+    }
+    candidates.shuffle(rng);
+    candidates.truncate(count);
+    Ok(candidates)
+}
+
+impl NetworkProperties {
+
+    pub fn seed_infection(&mut self, network_structure: &NetworkStructure, seeding: &Seeding, state: SeedState) -> Result<usize, SeedingError> {
+        // returns the number of nodes seeded, all of which were susceptible
+        let mut rng = self.rng_stream(3);
+        let n = self.nodal_states.len();
+        let susceptible: Vec<usize> = (0..n).filter(|&i| matches!(self.nodal_states[i], State::Susceptible)).collect();
+        let brackets = network_structure.age_brackets.iter().max().map_or(0, |x| x + 1);
+        let nodes: Vec<usize> = match seeding {
+            Seeding::Proportion(x) => {
+                let count = ((n as f64) * check_proportion(*x)?) as usize;
+                choose(susceptible, count, &mut rng)?
+            },
+            Seeding::Count(count) => choose(susceptible, *count, &mut rng)?,
+            Seeding::AgeCounts(counts) => {
+                if counts.len() > brackets {
+                    return Err(SeedingError::UnknownAgeBracket(counts.len() - 1))
+                }
+                let mut nodes: Vec<usize> = Vec::new();
+                for (bracket, count) in counts.iter().enumerate() {
+                    let candidates: Vec<usize> = susceptible.iter().copied().filter(|&i| network_structure.age_brackets[i] == bracket).collect();
+                    nodes.extend(choose(candidates, *count, &mut rng)?);
+                }
+                nodes
+            },
+            Seeding::DegreeQuantile { lower, upper, count } => {
+                let (lower, upper) = (check_proportion(*lower)?, check_proportion(*upper)?);
+                if lower > upper {
+                    return Err(SeedingError::InvalidQuantiles { lower, upper })
+                }
+                // rank by degree with random order within ties
+                let mut ranked: Vec<usize> = (0..n).collect();
+                ranked.shuffle(&mut rng);
+                ranked.sort_by(|a, b| network_structure.degree[*a].total_cmp(&network_structure.degree[*b]));
+                let range = ((lower * n as f64) as usize)..((upper * n as f64) as usize);
+                let candidates: Vec<usize> = ranked[range].iter().copied().filter(|&i| matches!(self.nodal_states[i], State::Susceptible)).collect();
+                choose(candidates, *count, &mut rng)?
+            },
+            Seeding::HighestDegree(count) => {
+                if *count > susceptible.len() {
+                    return Err(SeedingError::TooMany { requested: *count, available: susceptible.len() })
+                }
+                let mut ranked = susceptible;
+                ranked.shuffle(&mut rng);
+                ranked.sort_by(|a, b| network_structure.degree[*b].total_cmp(&network_structure.degree[*a]));
+                ranked.truncate(*count);
+                ranked
+            },
+            Seeding::Nodes(nodes) => {
+                if let Some(x) = nodes.iter().find(|&&x| x >= n) {
+                    return Err(SeedingError::UnknownNode(*x))
+                }
+                let mut nodes: Vec<usize> = nodes.iter().copied().filter(|&i| matches!(self.nodal_states[i], State::Susceptible)).collect();
+                nodes.sort_unstable();
+                nodes.dedup();
+                nodes
+            },
+            Seeding::Clustered(count) => {
+                if *count > susceptible.len() {
+                    return Err(SeedingError::TooMany { requested: *count, available: susceptible.len() })
+                }
+                let neighbours = network_structure.neighbours();
+                let mut chosen: Vec<bool> = vec![false; n];
+                let mut nodes: Vec<usize> = Vec::new();
+                let mut centres = susceptible.clone();
+                centres.shuffle(&mut rng);
+                for centre in centres.into_iter() {
+                    if nodes.len() == *count {
+                        break;
+                    }
+                    let mut cluster: Vec<usize> = neighbours[centre].iter().map(|(j, _)| *j).collect();
+                    cluster.shuffle(&mut rng);
+                    cluster.insert(0, centre);
+                    for i in cluster.into_iter() {
+                        if nodes.len() < *count && !chosen[i] && matches!(self.nodal_states[i], State::Susceptible) {
+                            chosen[i] = true;
+                            nodes.push(i);
+                        }
+                    }
+                }
+                nodes
+            }
+        };
+        self.seed_nodes(&nodes, &state, 0, &mut rng);
+        Ok(nodes.len())
+    }
+
+    pub(crate) fn seed_nodes<R: Rng>(&mut self, nodes: &[usize], state: &SeedState, day: usize, rng: &mut R) {
+        // periods are drawn whole, or as the time left for a node part way through
+        let distribution = match (state, &self.natural_history) {
+            (SeedState::Exposed, _) => self.latent_distribution(),
+            (SeedState::Infectious, Some(history)) => PeriodDistribution::Poisson { mean: history.presymptomatic_period },
+            (SeedState::Infectious, None) => self.infectious_distribution()
+        };
+        let residual_pmf = distribution.residual_pmf();
+        for &i in nodes.iter() {
+            let days = match self.seed_periods {
+                SeedPeriods::Full => distribution.sample(rng),
+                SeedPeriods::Residual => sample_pmf(&residual_pmf, rng)
+            };
+            self.nodal_states[i] = match (state, &self.natural_history) {
+                (SeedState::Exposed, _) => State::Exposed(days),
+                (SeedState::Infectious, Some(_)) => State::Presymptomatic(days),
+                (SeedState::Infectious, None) => State::Infected(days)
+            };
+            self.infection_day[i] = Some(day);
+            if let SeedState::Infectious = state {
+                self.infectious_day[i] = Some(day);
+            }
+        }
+    }
+
+    pub fn set_immunity(&mut self, network_structure: &NetworkStructure, seroprevalence: &[f64]) -> Result<usize, SeedingError> {
+        // recovered with the chance given for their age bracket, part way through their immunity.
+        // Returns the number of nodes made immune
+        let brackets = network_structure.age_brackets.iter().max().map_or(0, |x| x + 1);
+        if seroprevalence.len() > brackets {
+            return Err(SeedingError::UnknownAgeBracket(seroprevalence.len() - 1))
+        }
+        // nodes past the end of the age brackets have none, checked before anyone is made immune
+        if !seroprevalence.is_empty() && network_structure.age_brackets.len() < self.nodal_states.len() {
+            return Err(SeedingError::UnknownAgeBracket(brackets))
+        }
+        for x in seroprevalence.iter() {
+            check_proportion(*x)?;
+        }
+        let mut rng = self.rng_stream(2);
+        let residual_pmf = self.immune_distribution().residual_pmf();
+        let mut immune: usize = 0;
+        for i in 0..self.nodal_states.len() {
+            let p = network_structure.age_brackets
+                .get(i)
+                .and_then(|bracket| seroprevalence.get(*bracket))
+                .copied()
+                .unwrap_or(0.0);
+            if matches!(self.nodal_states[i], State::Susceptible) && rng.gen::<f64>() < p {
+                self.nodal_states[i] = State::Recovered(sample_pmf(&residual_pmf, &mut rng));
+                immune += 1;
+            }
+        }
+        Ok(immune)
+    }
+
+    pub fn immunity_from_file(&mut self, network_structure: &NetworkStructure, file_path: &str) -> Result<usize, SeedingError> {
+        let seroprevalence = seroprevalence_csv(file_path).map_err(SeedingError::Io)?;
+        self.set_immunity(network_structure, &seroprevalence)
+    }
+}
@@ -0,0 +1,254 @@
+use crate::interventions::{run_model_with_interventions, Intervention};
+use crate::random_graphs::*;
+use crate::seeding::SeedingError;
+use crate::write_to_file::read_severity_csv;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use rand_distr::Poisson;
+use serde::Serialize;
+
+// Hospital burden of the cases in a run. Symptomatic cases are admitted with an age specific
+// probability, and admitted cases go on to ICU or die with probabilities that are also by age.
+// Outcomes are decided at symptom onset, when the beds taken on the day of admission are known
+// for everyone with an earlier onset.
+
+#[derive(Clone, Debug, Default)]
+pub struct SeverityProbabilities {
+    // for each age bracket, the chance a symptomatic case is admitted to hospital
+    pub hospitalisation: Vec<f64>,
+    // for each age bracket, the chance an admitted case needs ICU, and that they die
+    pub icu: Vec<f64>,
+    pub death: Vec<f64>
+}
+
+impl SeverityProbabilities {
+    pub fn from_file(file_path: &str) -> SeverityProbabilities {
+        read_severity_csv(file_path)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SeverityDelays {
+    // mean days from symptom onset, each drawn from a Poisson distribution
+    pub onset_to_admission: f64,
+    pub onset_to_icu: f64,
+    pub onset_to_death: f64,
+    // mean days in hospital for those who are discharged
+    pub length_of_stay: f64
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Patient {
+    pub node: usize,
+    pub onset: usize,
+    pub admitted: usize,
+    pub icu: Option<usize>,
+    // day of discharge or death
+    pub discharged: usize,
+    pub died: bool,
+    // admitted while the beds were full
+    pub over_capacity: bool
+}
+
+#[derive(Clone, Debug)]
+pub struct Severity {
+    pub probabilities: SeverityProbabilities,
+    pub delays: SeverityDelays,
+    // beds above which the chances of ICU and death are multiplied by overflow_multiplier
+    pub bed_capacity: Option<usize>,
+    pub overflow_multiplier: f64,
+    pub patients: Vec<Patient>,
+    // beds taken on each day by the patients so far
+    beds: Vec<usize>,
+    symptomatic: Vec<bool>,
+    rng: StdRng
+}
+
+impl Severity {
+
+    pub fn new(probabilities: SeverityProbabilities, delays: SeverityDelays) -> Severity {
+        Severity {
+            probabilities,
+            delays,
+            bed_capacity: None,
+            overflow_multiplier: 1.0,
+            patients: Vec::new(),
+            beds: Vec::new(),
+            symptomatic: Vec::new(),
+            rng: StdRng::from_entropy()
+        }
+    }
+
+    pub fn with_capacity(mut self, bed_capacity: usize, overflow_multiplier: f64) -> Severity {
+        self.bed_capacity = Some(bed_capacity);
+        self.overflow_multiplier = overflow_multiplier;
+        self
+    }
+
+    pub fn seed(&mut self, seed: u64) {
+        // outcomes draw from their own generator, so they do not shift the draws of the epidemic
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    pub fn occupancy(&self, day: usize) -> usize {
+        self.beds.get(day).copied().unwrap_or(0)
+    }
+
+    fn delay(&mut self, mean: f64) -> usize {
+        if mean > 0.0 { Poisson::new(mean).unwrap().sample(&mut self.rng) as usize } else { 0 }
+    }
+
+    fn onset(&mut self, node: usize, age_bracket: usize, day: usize) {
+        let probability = |x: &Vec<f64>| x.get(age_bracket).copied().unwrap_or(0.0);
+        if self.rng.gen::<f64>() >= probability(&self.probabilities.hospitalisation) {
+            return
+        }
+        let admitted = day + self.delay(self.delays.onset_to_admission);
+        let over_capacity = self.bed_capacity.is_some_and(|x| self.occupancy(admitted) >= x);
+        let multiplier = if over_capacity { self.overflow_multiplier } else { 1.0 };
+        let needs_icu = self.rng.gen::<f64>() < (probability(&self.probabilities.icu) * multiplier).min(1.0);
+        let died = self.rng.gen::<f64>() < (probability(&self.probabilities.death) * multiplier).min(1.0);
+        let discharged = if died {
+            (day + self.delay(self.delays.onset_to_death)).max(admitted + 1)
+        } else {
+            admitted + 1 + self.delay(self.delays.length_of_stay)
+        };
+        // ICU is only entered while still in hospital
+        let icu_day = (day + self.delay(self.delays.onset_to_icu)).max(admitted);
+        if self.beds.len() < discharged {
+            self.beds.resize(discharged, 0);
+        }
+        for beds in self.beds[admitted..discharged].iter_mut() {
+            *beds += 1;
+        }
+        self.patients.push(Patient {
+            node,
+            onset: day,
+            admitted,
+            icu: if needs_icu { Some(icu_day.min(discharged - 1)) } else { None },
+            discharged,
+            died,
+            over_capacity
+        });
+    }
+}
+
+impl Intervention for Severity {
+    fn after_step(&mut self, network_structure: &mut NetworkStructure, network_properties: &mut NetworkProperties, day: usize) {
+        // symptom onsets of the day just simulated
+        let symptomatic: Vec<bool> = network_properties.nodal_states
+            .iter()
+            .map(|x| x.symptomatic())
+            .collect();
+        for (node, now) in symptomatic.iter().enumerate() {
+            if *now && !self.symptomatic.get(node).copied().unwrap_or(false) {
+                self.onset(node, network_structure.age_brackets.get(node).copied().unwrap_or(0), day + 1);
+            }
+        }
+        self.symptomatic = symptomatic;
+    }
+
+    fn record(&self, output: &mut Output) {
+        // runs until the last patient leaves, which can be after the end of the epidemic
+        let days = self.patients.iter().map(|x| x.discharged + 1).max().unwrap_or(0).max(output.seir.len());
+        output.admissions = vec![0; days];
+        output.hospital_occupancy = vec![0; days];
+        output.icu_occupancy = vec![0; days];
+        output.deaths = vec![0; days];
+        for patient in self.patients.iter() {
+            output.admissions[patient.admitted] += 1;
+            for day in patient.admitted..patient.discharged {
+                output.hospital_occupancy[day] += 1;
+            }
+            if let Some(icu) = patient.icu {
+                for day in icu..patient.discharged {
+                    output.icu_occupancy[day] += 1;
+                }
+            }
+            if patient.died {
+                output.deaths[patient.discharged] += 1;
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SeriesSummary {
+    // mean and 2.5% and 97.5% quantiles on each day, over the runs of an ensemble
+    pub mean: Vec<f64>,
+    pub lower: Vec<f64>,
+    pub upper: Vec<f64>
+}
+
+impl SeriesSummary {
+    pub fn new(series: &[Vec<usize>]) -> SeriesSummary {
+        // shorter runs are taken to be zero after they end
+        let days = series.iter().map(|x| x.len()).max().unwrap_or(0);
+        let mut summary = SeriesSummary::default();
+        for day in 0..days {
+            let mut values: Vec<f64> = series.iter().map(|x| x.get(day).copied().unwrap_or(0) as f64).collect();
+            values.sort_by(|a, b| a.total_cmp(b));
+            summary.mean.push(values.iter().sum::<f64>() / values.len() as f64);
+            summary.lower.push(quantile(&values, 0.025));
+            summary.upper.push(quantile(&values, 0.975));
+        }
+        summary
+    }
+}
+
+fn quantile(sorted: &[f64], q: f64) -> f64 {
+    let idx = ((sorted.len() - 1) as f64 * q).round() as usize;
+    sorted[idx]
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct EnsembleSummary {
+    pub runs: usize,
+    pub infectious: SeriesSummary,
+    pub admissions: SeriesSummary,
+    pub hospital_occupancy: SeriesSummary,
+    pub icu_occupancy: SeriesSummary,
+    pub deaths: SeriesSummary,
+    // totals over each run
+    pub total_admissions: SeriesSummary,
+    pub total_deaths: SeriesSummary,
+    pub peak_occupancy: SeriesSummary
+}
+
+pub fn summarise_ensemble(outputs: &[Output]) -> EnsembleSummary {
+    let series = |f: &dyn Fn(&Output) -> Vec<usize>| -> Vec<Vec<usize>> { outputs.iter().map(f).collect() };
+    // a single value per run
+    let totals = |f: &dyn Fn(&Output) -> usize| -> SeriesSummary {
+        SeriesSummary::new(&outputs.iter().map(|x| vec![f(x)]).collect::<Vec<Vec<usize>>>())
+    };
+    EnsembleSummary {
+        runs: outputs.len(),
+        infectious: SeriesSummary::new(&series(&|x| x.seir.iter().map(|y| y[2]).collect())),
+        admissions: SeriesSummary::new(&series(&|x| x.admissions.clone())),
+        hospital_occupancy: SeriesSummary::new(&series(&|x| x.hospital_occupancy.clone())),
+        icu_occupancy: SeriesSummary::new(&series(&|x| x.icu_occupancy.clone())),
+        deaths: SeriesSummary::new(&series(&|x| x.deaths.clone())),
+        total_admissions: totals(&|x| x.admissions.iter().sum()),
+        total_deaths: totals(&|x| x.deaths.iter().sum()),
+        peak_occupancy: totals(&|x| x.hospital_occupancy.iter().copied().max().unwrap_or(0))
+    }
+}
+
+pub fn run_ensemble_severity(network_structure: &NetworkStructure, network_properties: &NetworkProperties, maxtime: f64, initially_infected: f64, severity: &Severity, iterations: usize) -> Result<(Vec<Output>, EnsembleSummary), SeedingError> {
+    // independent runs from fresh copies of the properties, seeded one after another if a seed is set
+    let mut outputs: Vec<Output> = Vec::new();
+    for k in 0..iterations {
+        let mut properties = network_properties.clone();
+        let mut severity = severity.clone();
+        if let Some(seed) = network_properties.seed {
+            // wrapping, so a seed near u64::MAX does not overflow
+            let seed = seed.wrapping_add(k as u64);
+            properties.seed = Some(seed);
+            severity.seed(seed);
+        }
+        let mut interventions: Vec<Box<dyn Intervention>> = vec![Box::new(severity)];
+        outputs.push(run_model_with_interventions(network_structure, &mut properties, maxtime, initially_infected, &mut interventions)?);
+    }
+    let summary = summarise_ensemble(&outputs);
+    Ok((outputs, summary))
+}
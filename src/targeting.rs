@@ -0,0 +1,250 @@
+use crate::random_graphs::*;
+use crate::run_model::run_model;
+use crate::seeding::{SeedState, SeedingError};
+use crate::vaccination::VaccineStatus;
+use rand::prelude::*;
+use serde::Serialize;
+use std::collections::VecDeque;
+
+// Orders of priority for vaccination that depend on the contact network, and a runner comparing
+// the attack rate they achieve at a range of coverages.
+
+#[derive(Clone, Debug)]
+pub enum TargetingStrategy {
+    Degree,
+    // a random neighbour of a random node, which finds high degree nodes without knowing degrees
+    Acquaintance,
+    // betweenness estimated from the shortest paths out of a sample of source nodes
+    Betweenness { samples: usize },
+    // leading eigenvector of the adjacency matrix by power iteration
+    Eigenvector { iterations: usize },
+    KCore,
+    // highest degree first in each age bracket, covering the brackets at the same rate
+    DegreeWithinAge
+}
+
+#[derive(Debug, Serialize)]
+pub struct TargetingResult {
+    pub strategy: String,
+    pub coverage: f64,
+    pub attack_rate: f64
+}
+
+pub fn rank_nodes(network_structure: &NetworkStructure, strategy: &TargetingStrategy) -> Vec<usize> {
+    // nodes from first to last in line for vaccination
+    let mut rng: ThreadRng = rand::thread_rng();
+    let n = network_structure.degree.len();
+    let neighbours: Vec<Vec<usize>> = network_structure.neighbours()
+        .into_iter()
+        .map(|x| x.into_iter().map(|(j, _)| j).collect())
+        .collect();
+    let score: Vec<f64> = match strategy {
+        TargetingStrategy::Degree => network_structure.degree.clone(),
+        TargetingStrategy::Acquaintance => return acquaintance_order(&neighbours, &mut rng),
+        TargetingStrategy::Betweenness { samples } => betweenness(&neighbours, *samples, &mut rng),
+        TargetingStrategy::Eigenvector { iterations } => eigenvector(&neighbours, *iterations),
+        // ties between nodes of the same core broken by degree
+        TargetingStrategy::KCore => k_core(&neighbours)
+            .iter()
+            .zip(network_structure.degree.iter())
+            .map(|(core, degree)| (*core as f64) + degree / (n as f64 + 1.0))
+            .collect(),
+        TargetingStrategy::DegreeWithinAge => return degree_within_age(network_structure, &mut rng)
+    };
+    // random order within ties
+    let mut nodes: Vec<usize> = (0..n).collect();
+    nodes.shuffle(&mut rng);
+    nodes.sort_by(|a, b| score[*b].total_cmp(&score[*a]));
+    nodes
+}
+
+fn acquaintance_order(neighbours: &[Vec<usize>], rng: &mut ThreadRng) -> Vec<usize> {
+    let n = neighbours.len();
+    let mut chosen: Vec<bool> = vec![false; n];
+    let mut order: Vec<usize> = Vec::new();
+    // give up on the sampling once most draws land on nodes already chosen
+    let mut attempts: usize = 0;
+    while order.len() < n && attempts < 10*n {
+        attempts += 1;
+        let node = rng.gen_range(0..n);
+        if let Some(&acquaintance) = neighbours[node].choose(rng) {
+            if !chosen[acquaintance] {
+                chosen[acquaintance] = true;
+                order.push(acquaintance);
+            }
+        }
+    }
+    let mut rest: Vec<usize> = (0..n).filter(|x| !chosen[*x]).collect();
+    rest.shuffle(rng);
+    order.extend(rest);
+    order
+}
+
+fn betweenness(neighbours: &[Vec<usize>], samples: usize, rng: &mut ThreadRng) -> Vec<f64> {
+    // Brandes' accumulation from each sampled source, by breadth first search
+    let n = neighbours.len();
+    let mut centrality: Vec<f64> = vec![0.0; n];
+    let sources: Vec<usize> = (0..n).choose_multiple(rng, samples.min(n));
+    for source in sources.into_iter() {
+        let mut stack: Vec<usize> = Vec::new();
+        let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); n];
+        let mut paths: Vec<f64> = vec![0.0; n];
+        let mut distance: Vec<Option<usize>> = vec![None; n];
+        paths[source] = 1.0;
+        distance[source] = Some(0);
+        let mut queue: VecDeque<usize> = VecDeque::from([source]);
+        while let Some(v) = queue.pop_front() {
+            stack.push(v);
+            let d = distance[v].unwrap();
+            for &w in neighbours[v].iter() {
+                if distance[w].is_none() {
+                    distance[w] = Some(d + 1);
+                    queue.push_back(w);
+                }
+                if distance[w] == Some(d + 1) {
+                    paths[w] += paths[v];
+                    predecessors[w].push(v);
+                }
+            }
+        }
+        let mut dependency: Vec<f64> = vec![0.0; n];
+        while let Some(w) = stack.pop() {
+            for &v in predecessors[w].iter() {
+                dependency[v] += paths[v] / paths[w] * (1.0 + dependency[w]);
+            }
+            if w != source {
+                centrality[w] += dependency[w];
+            }
+        }
+    }
+    centrality
+}
+
+fn eigenvector(neighbours: &[Vec<usize>], iterations: usize) -> Vec<f64> {
+    let n = neighbours.len();
+    let mut x: Vec<f64> = vec![1.0; n];
+    for _ in 0..iterations {
+        // adding x itself shifts the spectrum so bipartite parts still converge
+        let mut next: Vec<f64> = x.clone();
+        for (i, row) in neighbours.iter().enumerate() {
+            for &j in row.iter() {
+                next[i] += x[j];
+            }
+        }
+        let norm = next.iter().map(|y| y*y).sum::<f64>().sqrt();
+        if norm == 0.0 {
+            break;
+        }
+        x = next.iter().map(|y| y / norm).collect();
+    }
+    x
+}
+
+pub fn k_core(neighbours: &[Vec<usize>]) -> Vec<usize> {
+    // core number of every node, by removing nodes of smallest remaining degree in turn
+    let n = neighbours.len();
+    let mut degree: Vec<usize> = neighbours.iter().map(|x| x.len()).collect();
+    let max_degree = degree.iter().copied().max().unwrap_or(0);
+    let mut buckets: Vec<Vec<usize>> = vec![Vec::new(); max_degree + 1];
+    for (node, d) in degree.iter().enumerate() {
+        buckets[*d].push(node);
+    }
+    let mut removed: Vec<bool> = vec![false; n];
+    let mut core: Vec<usize> = vec![0; n];
+    let mut k: usize = 0;
+    let mut d: usize = 0;
+    while d <= max_degree {
+        let node = match buckets[d].pop() {
+            Some(node) => node,
+            None => {
+                d += 1;
+                continue;
+            }
+        };
+        // buckets hold stale entries for nodes whose degree has since dropped
+        if removed[node] || degree[node] != d {
+            continue;
+        }
+        k = k.max(d);
+        core[node] = k;
+        removed[node] = true;
+        for &j in neighbours[node].iter() {
+            if !removed[j] && degree[j] > 0 {
+                degree[j] -= 1;
+                buckets[degree[j]].push(j);
+                d = d.min(degree[j]);
+            }
+        }
+    }
+    core
+}
+
+fn degree_within_age(network_structure: &NetworkStructure, rng: &mut ThreadRng) -> Vec<usize> {
+    let brackets = network_structure.age_brackets.iter().max().map_or(0, |x| x + 1);
+    let mut groups: Vec<Vec<usize>> = vec![Vec::new(); brackets];
+    for (node, age) in network_structure.age_brackets.iter().enumerate() {
+        groups[*age].push(node);
+    }
+    for group in groups.iter_mut() {
+        group.shuffle(rng);
+        group.sort_by(|a, b| network_structure.degree[*b].total_cmp(&network_structure.degree[*a]));
+    }
+    // next node from the bracket with the smallest share vaccinated so far
+    let mut taken: Vec<usize> = vec![0; brackets];
+    let mut order: Vec<usize> = Vec::new();
+    while let Some(bracket) = (0..brackets)
+        .filter(|b| taken[*b] < groups[*b].len())
+        .min_by(|a, b| {
+            let share = |x: usize| taken[x] as f64 / groups[x].len() as f64;
+            share(*a).total_cmp(&share(*b))
+        })
+    {
+        order.push(groups[bracket][taken[bracket]]);
+        taken[bracket] += 1;
+    }
+    order
+}
+
+pub fn compare_targeting(network_structure: &NetworkStructure, parameters: &[f64], strategies: &[TargetingStrategy], coverages: &[f64], maxtime: f64, initially_infected: f64, iterations: usize) -> Result<Vec<TargetingResult>, SeedingError> {
+    // mean attack rate over the iterations when the first nodes of each ranking are fully
+    // protected before the outbreak starts, seeded among the unprotected nodes
+    if !(0.0..=1.0).contains(&initially_infected) {
+        return Err(SeedingError::InvalidProportion(initially_infected))
+    }
+    let n = network_structure.degree.len();
+    let seeds = ((n as f64) * initially_infected) as usize;
+    let mut results: Vec<TargetingResult> = Vec::new();
+    for strategy in strategies.iter() {
+        let ranking = rank_nodes(network_structure, strategy);
+        for &coverage in coverages.iter() {
+            let vaccinated = ((n as f64) * coverage.clamp(0.0, 1.0)).round() as usize;
+            let mut infected: usize = 0;
+            for _ in 0..iterations {
+                let mut network_properties = NetworkProperties::new(network_structure);
+                network_properties.params(parameters.to_vec());
+                network_properties.vaccine_status = vec![VaccineStatus::default(); n];
+                for &node in ranking.iter().take(vaccinated) {
+                    network_properties.vaccine_status[node].doses = 1;
+                    network_properties.vaccine_status[node].infection_protection = 1.0;
+                }
+                // protected nodes could never have been infected, so they are not seeded either
+                let mut rng = network_properties.rng_stream(3);
+                let mut unprotected: Vec<usize> = (0..n)
+                    .filter(|&i| network_properties.vaccine_status[i].infection_protection < 1.0)
+                    .collect();
+                unprotected.shuffle(&mut rng);
+                unprotected.truncate(seeds);
+                network_properties.seed_nodes(&unprotected, &SeedState::Infectious, 0, &mut rng);
+                let output = run_model(network_structure, &mut network_properties, maxtime, 0.0)?;
+                // everyone infected at least once, which stays right when immunity wanes
+                infected += output.infection_day.iter().filter(|x| x.is_some()).count();
+            }
+            results.push(TargetingResult {
+                strategy: format!("{:?}", strategy),
+                coverage,
+                attack_rate: infected as f64 / ((n*iterations.max(1)) as f64)
+            });
+        }
+    }
+    Ok(results)
+}
@@ -0,0 +1,125 @@
+use crate::random_graphs::*;
+use crate::seeding::SeedingError;
+use crate::run_model::{run_days, step_model};
+use crate::write_to_file::read_temporal_csv;
+use nalgebra_sparse::coo::CooMatrix;
+use std::collections::HashMap;
+
+// Time stamped contacts, e.g. SocioPatterns t,i,j files. Each simulated day transmits only
+// along the contacts active during that day, with the total duration as the edge weight.
+
+#[derive(Clone, Debug)]
+pub struct TemporalEdge {
+    pub t: f64,
+    pub i: usize,
+    pub j: usize,
+    // length of the contact, one unless the file was read with durations
+    pub duration: f64
+}
+
+pub struct TemporalNetwork {
+    // contacts sorted by time, between nodes relabelled 0..n
+    pub edges: Vec<TemporalEdge>,
+    // original label of each node
+    pub ids: Vec<usize>,
+    pub age_brackets: Vec<usize>,
+    // length of a simulated day in the units of t, e.g. 86400 for seconds
+    pub time_step: f64,
+    // start again from the first contact once the data runs out
+    pub repeat: bool
+}
+
+impl TemporalNetwork {
+
+    pub fn new(mut edges: Vec<TemporalEdge>, time_step: f64) -> TemporalNetwork {
+        let mut labels: HashMap<usize, usize> = HashMap::new();
+        let mut ids: Vec<usize> = Vec::new();
+        for edge in edges.iter_mut() {
+            for node in [&mut edge.i, &mut edge.j] {
+                *node = *labels.entry(*node).or_insert_with(|| {
+                    ids.push(*node);
+                    ids.len() - 1
+                });
+            }
+        }
+        edges.sort_by(|a, b| a.t.total_cmp(&b.t));
+        TemporalNetwork {
+            edges,
+            age_brackets: vec![0; ids.len()],
+            ids,
+            time_step,
+            repeat: false
+        }
+    }
+
+    pub fn from_file(file_path: &str, time_step: f64) -> TemporalNetwork {
+        // every contact lasting one unit of time
+        TemporalNetwork::new(read_temporal_csv(file_path, false), time_step)
+    }
+
+    pub fn from_file_with_durations(file_path: &str, time_step: f64) -> TemporalNetwork {
+        // durations from the fourth column
+        TemporalNetwork::new(read_temporal_csv(file_path, true), time_step)
+    }
+
+    pub fn nodes(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn days(&self) -> usize {
+        // number of simulated days the data covers
+        match (self.edges.first(), self.edges.last()) {
+            (Some(first), Some(last)) => ((last.t - first.t) / self.time_step).floor() as usize + 1,
+            _ => 0
+        }
+    }
+
+    pub fn snapshot(&self, day: usize) -> NetworkStructure {
+        // contacts active during one simulated day, counting from the first contact
+        let day = match (self.repeat, self.days()) {
+            (_, 0) => day,
+            (true, days) => day % days,
+            (false, _) => day
+        };
+        let start = self.edges.first().map_or(0.0, |edge| edge.t) + (day as f64)*self.time_step;
+        let first = self.edges.partition_point(|edge| edge.t < start);
+        let last = self.edges.partition_point(|edge| edge.t < start + self.time_step);
+        self.aggregate(&self.edges[first..last], 1.0)
+    }
+
+    pub fn to_static(&self) -> NetworkStructure {
+        // every pair that ever met, weighted by their mean duration of contact per day
+        self.aggregate(&self.edges, self.days().max(1) as f64)
+    }
+
+    fn aggregate(&self, edges: &[TemporalEdge], days: f64) -> NetworkStructure {
+        let n = self.nodes();
+        let mut durations: HashMap<(usize, usize), f64> = HashMap::new();
+        for edge in edges.iter().filter(|edge| edge.i != edge.j) {
+            *durations.entry((edge.i.max(edge.j), edge.i.min(edge.j))).or_insert(0.0) += edge.duration;
+        }
+        let mut network_structure = NetworkStructure {
+            adjacency_matrix: CooMatrix::new(n,n),
+            degree: vec![0.0; n],
+            age_brackets: self.age_brackets.clone(),
+            household: Vec::new(),
+            layers: Vec::new(),
+            edge_layer: Vec::new()
+        };
+        // in a fixed order, so runs with the same seed match
+        let mut durations: Vec<((usize, usize), f64)> = durations.into_iter().collect();
+        durations.sort_unstable_by_key(|(pair, _)| *pair);
+        for ((i, j), duration) in durations.into_iter() {
+            network_structure.push_edge(i, j, duration / days, 0);
+        }
+        network_structure
+    }
+}
+
+pub fn run_model_temporal(temporal_network: &TemporalNetwork, network_properties: &mut NetworkProperties, maxtime: f64, initially_infected: f64) -> Result<Output, SeedingError> {
+    // as run_model, on the contacts of each day in turn. Durations are only used for
+    // transmission with TransmissionMode::Duration, where parameters[0] is per unit of duration
+    run_days(network_properties, maxtime, initially_infected, |network_properties, day, rng| {
+        step_model(&temporal_network.snapshot(day), network_properties, rng)
+    })
+}
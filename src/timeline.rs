@@ -0,0 +1,185 @@
+use crate::random_graphs::*;
+use crate::run_model::{run_days, step_model};
+use crate::seeding::SeedingError;
+use crate::useful_functions::{group_sizes, DistributionParameters};
+use crate::write_to_file::read_params_json;
+use nalgebra_sparse::coo::CooMatrix;
+use rand::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+// Changes to the contact network on given days of a run, e.g. moving between the CoMix periods
+// of fitting_parameters1.json and fitting_parameters2.json. Nodal states carry over unchanged.
+
+pub enum NetworkChange {
+    // continue on a different network over the same nodes
+    Swap(NetworkStructure),
+    // remove or add edges in each pair of age brackets to match the mean contacts of the
+    // distributions, keeping the rest of the network as it is
+    Rewire(DistributionParameters)
+}
+
+// the two nodes of a contact and its two entries in the adjacency matrix
+type Contact = (usize, usize, [usize; 2]);
+
+pub struct NetworkTimeline {
+    pub network: NetworkStructure,
+    pub partitions: Vec<usize>,
+    // changes and the day they happen, in order of day
+    pub changes: Vec<(usize, NetworkChange)>,
+    // days on which changes were applied
+    pub changed_on: Vec<usize>
+}
+
+impl NetworkTimeline {
+
+    pub fn new(network: NetworkStructure, partitions: Vec<usize>) -> NetworkTimeline {
+        NetworkTimeline { network, partitions, changes: Vec::new(), changed_on: Vec::new() }
+    }
+
+    pub fn add_change(&mut self, day: usize, change: NetworkChange) {
+        let idx = self.changes.partition_point(|(x, _)| *x <= day);
+        self.changes.insert(idx, (day, change));
+    }
+
+    pub fn swap_from_file(&mut self, day: usize, file_path: &str) {
+        // new configuration model network from another parameter file
+        let n = self.network.degree.len();
+        let network = NetworkStructure::new_molloy_reed(n, self.partitions.clone(), file_path);
+        self.add_change(day, NetworkChange::Swap(network));
+    }
+
+    pub fn rewire_from_file(&mut self, day: usize, file_path: &str) {
+        self.add_change(day, NetworkChange::Rewire(read_params_json(file_path)));
+    }
+
+    pub fn update(&mut self, day: usize) -> bool {
+        // apply the changes scheduled for this day, returning whether there were any
+        let due = self.changes.partition_point(|(x, _)| *x <= day);
+        for (_, change) in self.changes.drain(..due).collect::<Vec<_>>() {
+            match change {
+                NetworkChange::Swap(network) => {
+                    if network.degree.len() == self.network.degree.len() {
+                        self.network = network;
+                    } else {
+                        println!("The new network must have the same number of nodes, keeping the current one");
+                    }
+                },
+                NetworkChange::Rewire(dist_params) => self.rewire(&dist_params)
+            }
+        }
+        if due > 0 {
+            self.changed_on.push(day);
+        }
+        due > 0
+    }
+
+    fn rewire(&mut self, dist_params: &DistributionParameters) {
+        // only community contacts change, other layers such as households are left alone
+        let layer = if self.network.layers.is_empty() {
+            0
+        } else {
+            match self.network.layers.iter().position(|x| x == "community") {
+                Some(layer) => layer,
+                None => {
+                    println!("There is no community layer to rewire");
+                    return
+                }
+            }
+        };
+        let mut rng: ThreadRng = rand::thread_rng();
+        let group_sizes = group_sizes(&self.partitions);
+        let blocks = self.partitions.len();
+        let ages = &self.network.age_brackets;
+        // each contact is a pair of adjacency matrix entries, matched up so that a repeated
+        // pair of nodes stays two contacts
+        let mut upper: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        for (edge, (i, j, _)) in self.network.adjacency_matrix.triplet_iter().enumerate() {
+            if i < j && self.network.edge_layer(edge) == layer {
+                upper.entry((i, j)).or_default().push(edge);
+            }
+        }
+        // current contacts of each lower triangle pair of age brackets
+        let mut block_edges: Vec<Vec<Vec<Contact>>> = vec![vec![Vec::new(); blocks]; blocks];
+        // weights of the layer's contacts before rewiring, which new contacts are drawn from
+        let mut weights: Vec<f64> = Vec::new();
+        for (edge, (i, j, weight)) in self.network.adjacency_matrix.triplet_iter().enumerate() {
+            if i > j && self.network.edge_layer(edge) == layer {
+                weights.push(*weight);
+                if let Some(mirror) = upper.get_mut(&(j, i)).and_then(|x| x.pop()) {
+                    let (a, b) = (ages[i].max(ages[j]), ages[i].min(ages[j]));
+                    block_edges[a][b].push((i, j, [edge, mirror]));
+                }
+            }
+        }
+        let mut removed: HashSet<usize> = HashSet::new();
+        let mut added: Vec<(usize, usize)> = Vec::new();
+        for a in 0..blocks {
+            for b in 0..=a {
+                // contacts reported from both sides are averaged, within a bracket each edge counts twice
+                let target = if a == b {
+                    (group_sizes[a] as f64) * dist_params.distribution(a, a).mean() / 2.0
+                } else {
+                    ((group_sizes[a] as f64) * dist_params.distribution(a, b).mean()
+                        + (group_sizes[b] as f64) * dist_params.distribution(b, a).mean()) / 2.0
+                };
+                let target = target.round() as usize;
+                let edges = &mut block_edges[a][b];
+                if edges.len() > target {
+                    // thin uniformly at random
+                    edges.shuffle(&mut rng);
+                    removed.extend(edges.drain(target..).flat_map(|(_, _, entries)| entries));
+                } else {
+                    // new contacts between uniformly chosen members of the two brackets
+                    let (start_a, start_b) = (self.partitions[a] - group_sizes[a], self.partitions[b] - group_sizes[b]);
+                    let possible = if a == b {
+                        group_sizes[a] * group_sizes[a].saturating_sub(1) / 2
+                    } else {
+                        group_sizes[a] * group_sizes[b]
+                    };
+                    let mut existing: HashSet<(usize, usize)> = edges.iter().map(|(i, j, _)| (*i, *j)).collect();
+                    let mut count = edges.len();
+                    while count < target && existing.len() < possible {
+                        let i = rng.gen_range(start_a..self.partitions[a]);
+                        let j = rng.gen_range(start_b..self.partitions[b]);
+                        if i != j && existing.insert((i.max(j), i.min(j))) {
+                            added.push((i.max(j), i.min(j)));
+                            count += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        let n = self.network.degree.len();
+        let mut coo_mat: CooMatrix<f64> = CooMatrix::new(n,n);
+        let mut degree: Vec<f64> = vec![0.0; n];
+        let mut edge_layer: Vec<usize> = Vec::new();
+        for (edge, (i, j, weight)) in self.network.adjacency_matrix.triplet_iter().enumerate() {
+            if !removed.contains(&edge) {
+                coo_mat.push(i, j, *weight);
+                degree[i] += 1.0;
+                if !self.network.layers.is_empty() {
+                    edge_layer.push(self.network.edge_layer(edge));
+                }
+            }
+        }
+        self.network.adjacency_matrix = coo_mat;
+        self.network.degree = degree;
+        self.network.edge_layer = edge_layer;
+        for (i, j) in added.into_iter() {
+            let weight = weights.choose(&mut rng).copied().unwrap_or(1.0);
+            self.network.push_edge(i, j, weight, layer);
+        }
+    }
+}
+
+pub fn run_model_timeline(timeline: &mut NetworkTimeline, network_properties: &mut NetworkProperties, maxtime: f64, initially_infected: f64) -> Result<Output, SeedingError> {
+    // as run_model, applying the changes of the timeline at the start of their day
+    run_days(network_properties, maxtime, initially_infected, |network_properties, day, rng| {
+        // edge multipliers are indexed by entry, so they no longer match a changed network
+        if timeline.update(day) {
+            network_properties.edge_multipliers.clear();
+        }
+        step_model(&timeline.network, network_properties, rng)
+    })
+}
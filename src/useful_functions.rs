@@ -1,15 +1,59 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use rand_distr::StandardNormal;
+use nalgebra::DMatrix;
+use statrs::distribution::{ContinuousCDF, Normal};
+use crate::degree_distributions::{DegreeDistribution, DegreeSampler};
+use std::error::Error;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct DistributionParameters {
+    #[serde(default)]
     pub lambda: Vec<Vec<f64>>,
+    #[serde(default)]
     pub p_geom: Vec<Vec<f64>>,
-    pub p: Vec<Vec<f64>>
+    #[serde(default)]
+    pub p: Vec<Vec<f64>>,
+    // optional family for each block pair, overriding the matrices above
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub distributions: Vec<Vec<DegreeDistribution>>
 }
 
 impl DistributionParameters {
     pub fn new() -> DistributionParameters {
-        DistributionParameters { lambda: Vec::new(), p_geom: Vec::new(), p: Vec::new() }
+        DistributionParameters { lambda: Vec::new(), p_geom: Vec::new(), p: Vec::new(), distributions: Vec::new() }
+    }
+
+    pub fn blocks(&self) -> usize {
+        self.lambda.len().max(self.distributions.len())
+    }
+
+    pub fn distribution(&self, i: usize, j: usize) -> DegreeDistribution {
+        // a named family takes precedence over the poisson-geometric matrices
+        match self.distributions.get(i).and_then(|row| row.get(j)) {
+            Some(distribution) => distribution.clone(),
+            None => DegreeDistribution::PoissonGeometric { 
+                lambda: self.lambda[i][j], 
+                p_geom: self.p_geom[i][j], 
+                p: self.p[i][j] 
+            }
+        }
+    }
+
+    pub fn samplers(&self) -> Result<Vec<Vec<DegreeSampler>>, Box<dyn Error>> {
+        // a sampler for every block pair, or the first invalid distribution
+        (0..self.blocks())
+            .map(|i| (0..self.blocks()).map(|j| self.distribution(i, j).sampler()).collect())
+            .collect()
+    }
+
+    pub fn degree_pmf(&self, i: usize, j: usize) -> Vec<f64> {
+        self.distribution(i, j).pmf_vec()
+    }
+
+    pub fn degree_cdf(&self, i: usize, j: usize) -> Vec<f64> {
+        self.distribution(i, j).cdf_vec()
     }
 }
 
@@ -43,4 +87,118 @@ pub fn rates_to_probabilities(rates_mat: Vec<Vec<f64>>, partitions: &Vec<usize>)
             .collect()
         })
         .collect()
-}
\ No newline at end of file
+}
+
+pub fn balance_mixing(mixing_mat: &[Vec<f64>], totals: &[f64]) -> Vec<Vec<f64>> {
+    // symmetric matrix of contacts between brackets with row sums equal to totals, keeping
+    // the pattern of mixing_mat, by iterative proportional fitting of m[a][b] = x[a]*x[b]*k[a][b]
+    let blocks = totals.len();
+    let shares: Vec<Vec<f64>> = (0..blocks)
+        .map(|a| {
+            let row_total: f64 = mixing_mat[a].iter().sum();
+            mixing_mat[a].iter().map(|x| if row_total > 0.0 { totals[a] * x / row_total } else { 0.0 }).collect()
+        })
+        .collect();
+    let seed: Vec<Vec<f64>> = (0..blocks)
+        .map(|a| (0..blocks).map(|b| 0.5 * (shares[a][b] + shares[b][a])).collect())
+        .collect();
+    let mut scale: Vec<f64> = vec![1.0; blocks];
+    for _ in 0..10_000 {
+        let mut change: f64 = 0.0;
+        for a in 0..blocks {
+            let row: f64 = seed[a].iter().zip(scale.iter()).map(|(k, x)| k * x * scale[a]).sum();
+            if row > 0.0 {
+                // square root so that a bracket and its partners share the correction
+                let factor = (totals[a] / row).sqrt();
+                scale[a] *= factor;
+                change = change.max((factor - 1.0).abs());
+            }
+        }
+        if change < 1e-12 {
+            break;
+        }
+    }
+    let balanced: Vec<Vec<f64>> = (0..blocks)
+        .map(|a| (0..blocks).map(|b| scale[a] * scale[b] * seed[a][b]).collect())
+        .collect();
+    for (a, row) in balanced.iter().enumerate() {
+        let total: f64 = row.iter().sum();
+        if (total - totals[a]).abs() > 1e-6 * totals[a].max(1.0) {
+            println!("Mixing matrix cannot give age bracket {} its {:.1} contacts, only {:.1}", a, totals[a], total);
+        }
+    }
+    balanced
+}
+
+pub fn group_sizes(partitions: &[usize]) -> Vec<usize> {
+    // partitions hold the cumulative upper index of each age bracket
+    let mut sizes: Vec<usize> = partitions
+        .windows(2)
+        .map(|pair| pair[1] - pair[0])
+        .collect();
+    sizes.insert(0, partitions[0]);
+    sizes
+}
+
+pub fn spectral_radius(matrix: &[Vec<f64>]) -> f64 {
+    // largest eigenvalue modulus, used for next generation matrices
+    let m = matrix.len();
+    if m == 0 {
+        return 0.0
+    }
+    let mat = DMatrix::from_fn(m, m, |i, j| matrix[i][j]);
+    mat.complex_eigenvalues()
+        .iter()
+        .map(|x| x.re.hypot(x.im))
+        .fold(0.0, f64::max)
+}
+
+pub fn correlated_degree<R: Rng>(cdf: &[f64], activity: f64, correlation: f64, rng: &mut R) -> usize {
+    // gaussian copula, the node's activity is mixed with fresh noise and mapped through
+    // the degree cdf so the marginal distribution of each bracket is unchanged
+    let z = correlation.sqrt()*activity + (1.0 - correlation).sqrt()*rng.sample::<f64, _>(StandardNormal);
+    let u = Normal::new(0.0, 1.0).unwrap().cdf(z);
+    // an empty cdf has no degrees to choose from, so gives none
+    cdf.partition_point(|&x| x < u).min(cdf.len().saturating_sub(1))
+}
+
+pub fn total_degree_variance(dist_params: &DistributionParameters, bracket: usize, correlation: f64, samples: usize) -> f64 {
+    // monte carlo variance of a node's total contacts over all brackets, with a fixed seed
+    // so that the estimate is a smooth function of the correlation
+    let mut rng = StdRng::seed_from_u64(0);
+    let cdfs: Vec<Vec<f64>> = (0..dist_params.blocks())
+        .map(|j| dist_params.degree_cdf(bracket, j))
+        .collect();
+    let totals: Vec<f64> = (0..samples)
+        .map(|_| {
+            let activity: f64 = rng.sample(StandardNormal);
+            cdfs.iter()
+                .map(|cdf| correlated_degree(cdf, activity, correlation, &mut rng) as f64)
+                .sum()
+        })
+        .collect();
+    let mean = totals.iter().sum::<f64>() / (samples as f64);
+    totals.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / ((samples - 1) as f64)
+}
+
+pub fn calibrate_activity_correlation(dist_params: &DistributionParameters, bracket: usize, target_variance: f64) -> f64 {
+    // bisection on the correlation so the total contact variance matches the survey,
+    // clamped to the independent and fully correlated extremes
+    let samples = 20_000;
+    let (mut lower, mut upper) = (0.0, 1.0);
+    if target_variance <= total_degree_variance(dist_params, bracket, lower, samples) {
+        return lower
+    }
+    if target_variance >= total_degree_variance(dist_params, bracket, upper, samples) {
+        return upper
+    }
+    for _ in 0..30 {
+        let mid = 0.5*(lower + upper);
+        if total_degree_variance(dist_params, bracket, mid, samples) < target_variance {
+            lower = mid;
+        } else {
+            upper = mid;
+        }
+    }
+    0.5*(lower + upper)
+}
@@ -0,0 +1,240 @@
+use crate::interventions::Intervention;
+use crate::random_graphs::*;
+use crate::targeting::{rank_nodes, TargetingStrategy};
+use rand::prelude::*;
+use serde::Serialize;
+use std::collections::VecDeque;
+
+// Vaccination campaigns run as an Intervention. Each node keeps its doses and current protection
+// in NetworkProperties::vaccine_status, which scales the probability of transmission to and from it.
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct VaccineStatus {
+    pub doses: usize,
+    pub last_dose: usize,
+    // reduction in the chance of being infected, and of infecting others once infected
+    pub infection_protection: f64,
+    pub transmission_protection: f64
+}
+
+#[derive(Clone, Debug)]
+pub enum VaccineAction {
+    // every vaccinated node is partially protected
+    Leaky,
+    // a share of vaccinated nodes equal to the efficacy is fully protected, the rest not at all
+    AllOrNothing
+}
+
+#[derive(Clone, Debug)]
+pub struct Vaccine {
+    // efficacy after each dose, the number of doses is the length
+    pub efficacy_infection: Vec<f64>,
+    pub efficacy_transmission: Vec<f64>,
+    pub action: VaccineAction,
+    // days between doses
+    pub dose_interval: usize,
+    // days for protection to halve after the last dose, None for no waning
+    pub waning_half_life: Option<f64>
+}
+
+#[derive(Clone, Debug)]
+pub enum Prioritisation {
+    // whole age brackets in the given order, e.g. oldest first
+    AgeOrder(Vec<usize>),
+    // highest degree first
+    Degree,
+    Random,
+    // any of the network based rankings of the targeting module
+    Targeted(TargetingStrategy),
+    // contacts of nodes detected at symptom onset, with the given probability
+    Ring { detection_probability: f64 }
+}
+
+pub struct VaccinationCampaign {
+    pub vaccine: Vaccine,
+    pub prioritisation: Prioritisation,
+    pub daily_capacity: usize,
+    pub start_day: usize,
+    // doses given on each day of the run
+    pub doses_delivered: Vec<usize>,
+    queue: VecDeque<usize>,
+    infectious: Vec<bool>
+}
+
+impl VaccinationCampaign {
+
+    pub fn new(vaccine: Vaccine, prioritisation: Prioritisation, daily_capacity: usize, start_day: usize) -> VaccinationCampaign {
+        VaccinationCampaign {
+            vaccine,
+            prioritisation,
+            daily_capacity,
+            start_day,
+            doses_delivered: Vec::new(),
+            queue: VecDeque::new(),
+            infectious: Vec::new()
+        }
+    }
+
+    fn first_dose_order(&self, network_structure: &NetworkStructure) -> VecDeque<usize> {
+        let mut rng: ThreadRng = rand::thread_rng();
+        let mut nodes: Vec<usize> = (0..network_structure.degree.len()).collect();
+        // random order within ties
+        nodes.shuffle(&mut rng);
+        match &self.prioritisation {
+            Prioritisation::AgeOrder(order) => {
+                if network_structure.age_brackets.len() < nodes.len() {
+                    println!("vaccination by age order needs an age bracket for every node, {} of {} have one", network_structure.age_brackets.len(), nodes.len());
+                }
+                // nodes without a bracket, or in a bracket not in the order, are not vaccinated
+                let rank = |node: &usize| {
+                    network_structure.age_brackets
+                        .get(*node)
+                        .and_then(|bracket| order.iter().position(|x| x == bracket))
+                };
+                nodes.retain(|node| rank(node).is_some());
+                nodes.sort_by_key(rank);
+            },
+            Prioritisation::Degree => nodes.sort_by(|a, b| network_structure.degree[*b].total_cmp(&network_structure.degree[*a])),
+            Prioritisation::Random => (),
+            Prioritisation::Targeted(strategy) => nodes = rank_nodes(network_structure, strategy),
+            // filled as cases are detected
+            Prioritisation::Ring { .. } => nodes.clear()
+        }
+        nodes.into_iter().collect()
+    }
+
+    fn give_dose(&self, status: &mut VaccineStatus, day: usize, rng: &mut ThreadRng) {
+        let dose = status.doses.min(self.vaccine.efficacy_infection.len() - 1);
+        let efficacy = self.vaccine.efficacy_infection[dose];
+        status.infection_protection = match self.vaccine.action {
+            VaccineAction::Leaky => efficacy,
+            VaccineAction::AllOrNothing => {
+                // unprotected nodes become protected with the chance that tops them up to the new efficacy
+                let previous = if dose > 0 { self.vaccine.efficacy_infection[dose - 1] } else { 0.0 };
+                if status.infection_protection >= 1.0 || rng.gen::<f64>() < (efficacy - previous) / (1.0 - previous) { 1.0 } else { 0.0 }
+            }
+        };
+        status.transmission_protection = self.vaccine.efficacy_transmission.get(dose).copied().unwrap_or(0.0);
+        status.doses += 1;
+        status.last_dose = day;
+    }
+
+    fn wane(&self, status: &mut VaccineStatus, day: usize, rng: &mut ThreadRng) {
+        let half_life = match self.vaccine.waning_half_life {
+            Some(x) if status.doses > 0 => x,
+            _ => return
+        };
+        let dose = status.doses.min(self.vaccine.efficacy_infection.len()) - 1;
+        let remaining = 0.5f64.powf((day - status.last_dose) as f64 / half_life);
+        status.infection_protection = match self.vaccine.action {
+            VaccineAction::Leaky => self.vaccine.efficacy_infection[dose] * remaining,
+            // protected nodes lose their protection at the daily rate of the half life
+            VaccineAction::AllOrNothing => {
+                if status.infection_protection > 0.0 && rng.gen::<f64>() < 1.0 - 0.5f64.powf(1.0 / half_life) { 0.0 } else { status.infection_protection }
+            }
+        };
+        status.transmission_protection = self.vaccine.efficacy_transmission.get(dose).copied().unwrap_or(0.0) * remaining;
+    }
+}
+
+impl Intervention for VaccinationCampaign {
+    fn before_step(&mut self, network_structure: &mut NetworkStructure, network_properties: &mut NetworkProperties, day: usize) {
+        let n = network_structure.degree.len();
+        if network_properties.vaccine_status.len() < n {
+            network_properties.vaccine_status.resize(n, VaccineStatus::default());
+        }
+        if day == self.start_day {
+            // after any ring contacts queued the day before
+            let order = self.first_dose_order(network_structure);
+            self.queue.extend(order);
+        }
+        if day < self.start_day || self.vaccine.efficacy_infection.is_empty() {
+            self.doses_delivered.push(0);
+            return
+        }
+        let mut rng: ThreadRng = rand::thread_rng();
+        for status in network_properties.vaccine_status.iter_mut() {
+            self.wane(status, day, &mut rng);
+        }
+        // nodes known to be infected, through their symptoms, are not vaccinated
+        let eligible = |node: usize, network_properties: &NetworkProperties| !network_properties.nodal_states[node].symptomatic();
+        let mut delivered: usize = 0;
+        // later doses that are due come before first doses
+        let due: Vec<usize> = (0..n)
+            .filter(|&node| {
+                let status = &network_properties.vaccine_status[node];
+                status.doses > 0 && status.doses < self.vaccine.efficacy_infection.len()
+                    && day >= status.last_dose + self.vaccine.dose_interval
+            })
+            .collect();
+        for node in due.into_iter() {
+            if delivered == self.daily_capacity {
+                break;
+            }
+            if eligible(node, network_properties) {
+                let mut status = network_properties.vaccine_status[node].clone();
+                self.give_dose(&mut status, day, &mut rng);
+                network_properties.vaccine_status[node] = status;
+                delivered += 1;
+            }
+        }
+        // nodes that are not eligible today keep their place in the queue for a later day
+        let mut deferred: Vec<usize> = Vec::new();
+        while delivered < self.daily_capacity {
+            let node = match self.queue.pop_front() {
+                Some(node) => node,
+                None => break
+            };
+            if network_properties.vaccine_status[node].doses > 0 {
+                continue;
+            }
+            if eligible(node, network_properties) {
+                let mut status = network_properties.vaccine_status[node].clone();
+                self.give_dose(&mut status, day, &mut rng);
+                network_properties.vaccine_status[node] = status;
+                delivered += 1;
+            } else {
+                deferred.push(node);
+            }
+        }
+        for node in deferred.into_iter().rev() {
+            self.queue.push_front(node);
+        }
+        self.doses_delivered.push(delivered);
+    }
+
+    fn after_step(&mut self, network_structure: &mut NetworkStructure, network_properties: &mut NetworkProperties, day: usize) {
+        // ring vaccination queues the contacts of newly infectious nodes that are detected
+        let detection_probability = match self.prioritisation {
+            Prioritisation::Ring { detection_probability } if day + 1 >= self.start_day => detection_probability,
+            _ => return
+        };
+        let infectious: Vec<bool> = network_properties.nodal_states
+            .iter()
+            .map(|x| x.symptomatic())
+            .collect();
+        let mut rng: ThreadRng = rand::thread_rng();
+        let mut detected: Vec<bool> = vec![false; infectious.len()];
+        for (node, now) in infectious.iter().enumerate() {
+            if *now && !self.infectious.get(node).copied().unwrap_or(false) && rng.gen::<f64>() < detection_probability {
+                detected[node] = true;
+            }
+        }
+        for (i, j, _) in network_structure.adjacency_matrix.triplet_iter() {
+            if detected[i] && network_properties.vaccine_status.get(j).is_none_or(|x| x.doses == 0) {
+                self.queue.push_back(j);
+            }
+        }
+        self.infectious = infectious;
+    }
+
+    fn record(&self, output: &mut Output) {
+        // summed over every campaign of the run
+        if output.doses_delivered.len() < self.doses_delivered.len() {
+            output.doses_delivered.resize(self.doses_delivered.len(), 0);
+        }
+        for (total, doses) in output.doses_delivered.iter_mut().zip(self.doses_delivered.iter()) {
+            *total += doses;
+        }
+    }
+}
@@ -1,6 +1,11 @@
 use csv::Writer;
 use crate::random_graphs::{Output, ResultType};
 use crate::useful_functions::DistributionParameters;
+use crate::fitting::{ContactRecord, ParticipantRecord};
+use crate::households::HouseholdParameters;
+use crate::edge_weights::ContactWeight;
+use crate::temporal_network::TemporalEdge;
+use crate::severity::SeverityProbabilities;
 use serde::Serialize;
 use serde_json;
 use std::io::{Write,Read};
@@ -68,6 +73,8 @@ fn params_json(file_path: &str) -> Result<DistributionParameters, Box<dyn std::e
     file.read_to_string(&mut content)?;
 
     let my_struct: DistributionParameters = serde_json::from_str(&content)?;
+    // reject distributions that cannot be sampled, e.g. an empirical pmf of zeros
+    my_struct.samplers()?;
 
     Ok(my_struct)
 }
@@ -84,6 +91,47 @@ pub fn read_rates_mat(file_path: &str) -> Vec<Vec<f64>> {
     rates_mat
 }
 
+fn household_json(file_path: &str) -> Result<HouseholdParameters, Box<dyn std::error::Error>> {
+    let mut file = File::open(file_path)?;
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+
+    let my_struct: HouseholdParameters = serde_json::from_str(&content)?;
+
+    Ok(my_struct)
+}
+
+pub fn read_household_json(file_path: &str) -> HouseholdParameters {
+    match household_json(file_path) {
+        Ok(my_struct) => my_struct,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            HouseholdParameters::new()
+        }
+    }
+}
+
+fn weights_json(file_path: &str) -> Result<Vec<Vec<ContactWeight>>, Box<dyn std::error::Error>> {
+    let mut file = File::open(file_path)?;
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+
+    let weights: Vec<Vec<ContactWeight>> = serde_json::from_str(&content)?;
+
+    Ok(weights)
+}
+
+pub fn read_weights_json(file_path: &str) -> Vec<Vec<ContactWeight>> {
+    // weight distribution of each pair of age brackets, missing pairs have weight one
+    match weights_json(file_path) {
+        Ok(weights) => weights,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            Vec::new()
+        }
+    }
+}
+
 pub fn read_params_json(file_path: &str) -> DistributionParameters {
     // let file_path = "model_input_files/fitting_parameters1.json";
     let my_struct = match params_json(&file_path) {
@@ -94,4 +142,187 @@ pub fn read_params_json(file_path: &str) -> DistributionParameters {
         }
     };
     my_struct
-}
\ No newline at end of file
+}
+
+pub fn read_contact_records(file_path: &str) -> Result<Vec<ContactRecord>, Box<dyn std::error::Error>> {
+    // one row per participant and contact age, with a header naming the ContactRecord fields
+    let mut reader = csv::Reader::from_path(file_path)?;
+    let mut records: Vec<ContactRecord> = Vec::new();
+    for result in reader.deserialize() {
+        records.push(result?);
+    }
+    Ok(records)
+}
+
+pub fn read_participant_records(file_path: &str) -> Result<Vec<ParticipantRecord>, Box<dyn std::error::Error>> {
+    // one row per participant, with a header naming the ParticipantRecord fields
+    let mut reader = csv::Reader::from_path(file_path)?;
+    let mut records: Vec<ParticipantRecord> = Vec::new();
+    for result in reader.deserialize() {
+        records.push(result?);
+    }
+    Ok(records)
+}
+
+fn temporal_csv(file_path: &str, with_duration: bool) -> Result<Vec<TemporalEdge>, Box<dyn std::error::Error>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_path(file_path)?;
+    let mut edges: Vec<TemporalEdge> = Vec::new();
+    for (row, result) in reader.records().enumerate() {
+        let record = result?;
+        // skip a header line if there is one
+        if row == 0 && record.get(0).is_none_or(|x| x.parse::<f64>().is_err()) {
+            continue;
+        }
+        if record.len() < 3 {
+            return Err(format!("row {} needs t, i and j columns", row + 1).into())
+        }
+        edges.push(TemporalEdge {
+            t: record[0].parse()?,
+            i: record[1].parse()?,
+            j: record[2].parse()?,
+            duration: match (with_duration, record.get(3)) {
+                (true, Some(x)) => x.parse()?,
+                (true, None) => return Err(format!("row {} has no duration column", row + 1).into()),
+                (false, _) => 1.0
+            }
+        });
+    }
+    Ok(edges)
+}
+
+pub fn read_temporal_csv(file_path: &str, with_duration: bool) -> Vec<TemporalEdge> {
+    // rows of t,i,j, with a fourth duration column if with_duration is set. Any further columns
+    // are ignored, e.g. the class labels of SocioPatterns files
+    match temporal_csv(file_path, with_duration) {
+        Ok(edges) => edges,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            Vec::new()
+        }
+    }
+}
+
+fn severity_csv(file_path: &str) -> Result<SeverityProbabilities, Box<dyn std::error::Error>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .trim(csv::Trim::All)
+        .from_path(file_path)?;
+    let mut probabilities = SeverityProbabilities::default();
+    for (row, result) in reader.records().enumerate() {
+        let record = result?;
+        // skip a header line if there is one
+        if row == 0 && record.get(0).is_none_or(|x| x.parse::<f64>().is_err()) {
+            continue;
+        }
+        if record.len() < 3 {
+            return Err(format!("row {} needs hospitalisation, icu and death columns", row + 1).into())
+        }
+        probabilities.hospitalisation.push(record[0].parse()?);
+        probabilities.icu.push(record[1].parse()?);
+        probabilities.death.push(record[2].parse()?);
+    }
+    Ok(probabilities)
+}
+
+pub fn read_severity_csv(file_path: &str) -> SeverityProbabilities {
+    // one row per age bracket of hospitalisation, icu and death probabilities
+    match severity_csv(file_path) {
+        Ok(probabilities) => probabilities,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            SeverityProbabilities::default()
+        }
+    }
+}
+
+fn indexed_values_csv(file_path: &str, carry_forward: bool) -> Result<Vec<f64>, Box<dyn std::error::Error>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_path(file_path)?;
+    let mut values: Vec<f64> = Vec::new();
+    for (row, result) in reader.records().enumerate() {
+        let record = result?;
+        // skip a header line if there is one
+        if row == 0 && record.get(0).is_none_or(|x| x.parse::<f64>().is_err()) {
+            continue;
+        }
+        // either a value per row, or the index, e.g. a day, followed by its value
+        match record.len() {
+            1 => values.push(record[0].parse()?),
+            _ => {
+                let day: usize = record[0].parse()?;
+                if values.len() <= day {
+                    // indices left out take the last value given, or zero for probabilities
+                    let fill = match (carry_forward, values.last()) {
+                        (false, _) => 0.0,
+                        (true, Some(x)) => *x,
+                        (true, None) if day == 0 => 0.0,
+                        (true, None) => return Err(format!("row {} starts at {} but there is no value for 0", row + 1, day).into())
+                    };
+                    values.resize(day + 1, fill);
+                } else if carry_forward {
+                    return Err(format!("row {} is out of order, the indices must increase", row + 1).into())
+                }
+                values[day] = record[1].parse()?;
+            }
+        }
+    }
+    Ok(values)
+}
+
+pub fn read_period_csv(file_path: &str) -> Vec<f64> {
+    // probability of each number of days, from 0 upwards
+    match indexed_values_csv(file_path, false) {
+        Ok(pmf) => pmf,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            vec![1.0]
+        }
+    }
+}
+
+pub fn read_transmission_csv(file_path: &str) -> Vec<f64> {
+    // transmission multiplier for each day from day 0, where day,value rows hold each value
+    // until the next row
+    match indexed_values_csv(file_path, true) {
+        Ok(values) => values,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            Vec::new()
+        }
+    }
+}
+
+pub(crate) fn seroprevalence_csv(file_path: &str) -> Result<Vec<f64>, Box<dyn std::error::Error>> {
+    // share of each age bracket already immune from bracket 0, where bracket,value rows hold
+    // each value until the next row
+    indexed_values_csv(file_path, true)
+}
+
+pub fn read_seroprevalence_csv(file_path: &str) -> Vec<f64> {
+    match seroprevalence_csv(file_path) {
+        Ok(values) => values,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            Vec::new()
+        }
+    }
+}
+
+pub fn read_importation_csv(file_path: &str) -> Vec<f64> {
+    // mean number of imported infections on each day from day 0, where day,value rows hold each
+    // value until the next row
+    match indexed_values_csv(file_path, true) {
+        Ok(values) => values,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            Vec::new()
+        }
+    }
+}